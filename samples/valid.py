@@ -27,11 +27,9 @@ def func(int_val, float_val):
 
     return 1
 
-    #! dicts foram ignorados no sintatico
-
-    # d = {
-    #     "key": value
-    # }
+    d = {
+        "key": value
+    }
 
 if __name__ == "__main__":
     func(34, 10.0)
\ No newline at end of file
@@ -0,0 +1,3267 @@
+// A arvore so e lida pelo dump (Debug), entao varios campos nao sao usados
+#![allow(dead_code)]
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+use std::iter::once;
+
+use super::diagnostics::{Diagnostic, ErrorCode};
+use super::{CompilationError, LiteralTypes, ParseTree, PossibleStates, TkType, Token};
+
+#[derive(Debug)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub row: usize,
+    pub col: usize
+}
+
+#[derive(Debug)]
+pub enum StmtKind {
+    FunctionDef {
+        name: String,
+        args: Arguments,
+        body: Vec<Stmt>,
+        decorator_list: Vec<Expr>,
+        returns: Option<Expr>,
+        is_async: bool,
+        is_generator: bool
+    },
+    ClassDef {
+        name: String,
+        bases: Vec<Expr>,
+        keywords: Vec<Keyword>,
+        body: Vec<Stmt>,
+        decorator_list: Vec<Expr>
+    },
+    Return(Option<Expr>),
+    Assign {
+        targets: Vec<Expr>,
+        value: Expr
+    },
+    AugAssign {
+        target: Expr,
+        op: Operator,
+        value: Expr
+    },
+    AnnAssign {
+        target: Expr,
+        annotation: Expr,
+        value: Option<Expr>,
+        simple: bool
+    },
+    For {
+        target: Expr,
+        iter: Expr,
+        body: Vec<Stmt>,
+        orelse: Vec<Stmt>,
+        is_async: bool
+    },
+    While {
+        test: Expr,
+        body: Vec<Stmt>,
+        orelse: Vec<Stmt>
+    },
+    If {
+        test: Expr,
+        body: Vec<Stmt>,
+        orelse: Vec<Stmt>
+    },
+    With {
+        items: Vec<WithItem>,
+        body: Vec<Stmt>,
+        is_async: bool
+    },
+    Match {
+        subject: Expr,
+        cases: Vec<MatchCase>
+    },
+    TypeAlias {
+        name: Expr,
+        type_params: Vec<TypeParam>,
+        value: Expr
+    },
+    Delete(Vec<Expr>),
+    Global(Vec<String>),
+    Nonlocal(Vec<String>),
+    Assert {
+        test: Expr,
+        msg: Option<Expr>
+    },
+    // so existem no python 2
+    Print {
+        dest: Option<Expr>,
+        values: Vec<Expr>,
+        nl: bool
+    },
+    Exec {
+        body: Expr,
+        globals: Option<Expr>,
+        locals: Option<Expr>
+    },
+    Expr(Expr),
+    Pass,
+    Break,
+    Continue
+}
+
+#[derive(Debug)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub row: usize,
+    pub col: usize,
+    pub end_row: usize,
+    pub end_col: usize
+}
+
+#[derive(Debug)]
+pub enum ExprKind {
+    BoolOp {
+        op: BoolOperator,
+        values: Vec<Expr>
+    },
+    NamedExpr {
+        target: Box<Expr>,
+        value: Box<Expr>
+    },
+    BinOp {
+        left: Box<Expr>,
+        op: Operator,
+        right: Box<Expr>
+    },
+    UnaryOp {
+        op: UnaryOperator,
+        operand: Box<Expr>
+    },
+    Lambda {
+        args: Arguments,
+        body: Box<Expr>
+    },
+    IfExp {
+        test: Box<Expr>,
+        body: Box<Expr>,
+        orelse: Box<Expr>
+    },
+    Await(Box<Expr>),
+    Yield(Option<Box<Expr>>),
+    YieldFrom(Box<Expr>),
+    Compare {
+        left: Box<Expr>,
+        ops: Vec<CmpOperator>,
+        comparators: Vec<Expr>
+    },
+    Call {
+        func: Box<Expr>,
+        args: Vec<Expr>,
+        keywords: Vec<Keyword>
+    },
+    Constant(Constant),
+    // o conteudo das f-strings nao e analisado, fica o lexema inteiro
+    JoinedStr(String),
+    Attribute {
+        value: Box<Expr>,
+        attr: String
+    },
+    Subscript {
+        value: Box<Expr>,
+        slice: Box<Expr>
+    },
+    Dict {
+        keys: Vec<Option<Expr>>,
+        values: Vec<Expr>
+    },
+    Set(Vec<Expr>),
+    ListComp {
+        elt: Box<Expr>,
+        generators: Vec<Comprehension>
+    },
+    SetComp {
+        elt: Box<Expr>,
+        generators: Vec<Comprehension>
+    },
+    DictComp {
+        key: Box<Expr>,
+        value: Box<Expr>,
+        generators: Vec<Comprehension>
+    },
+    GeneratorExp {
+        elt: Box<Expr>,
+        generators: Vec<Comprehension>
+    },
+    Starred(Box<Expr>),
+    Name(String),
+    List(Vec<Expr>),
+    Tuple(Vec<Expr>),
+    Slice {
+        lower: Option<Box<Expr>>,
+        upper: Option<Box<Expr>>,
+        step: Option<Box<Expr>>
+    }
+}
+
+#[derive(Debug)]
+pub enum Constant {
+    Int(String),
+    Float(String),
+    Imaginary(String),
+    Str(String),
+    Bool(bool),
+    None,
+    Ellipsis
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BoolOperator {
+    And,
+    Or
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operator {
+    Add,
+    Sub,
+    Mult,
+    MatMult,
+    Div,
+    Mod,
+    Pow,
+    LShift,
+    RShift,
+    BitOr,
+    BitXor,
+    BitAnd,
+    FloorDiv
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UnaryOperator {
+    Invert,
+    Not,
+    UAdd,
+    USub
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CmpOperator {
+    Eq,
+    NotEq,
+    Lt,
+    LtE,
+    Gt,
+    GtE,
+    Is,
+    IsNot,
+    In,
+    NotIn
+}
+
+#[derive(Debug, Default)]
+pub struct Arguments {
+    pub posonlyargs: Vec<Arg>,
+    pub args: Vec<Arg>,
+    pub vararg: Option<Arg>,
+    pub kwonlyargs: Vec<Arg>,
+    pub kwarg: Option<Arg>
+}
+
+#[derive(Debug)]
+pub struct Arg {
+    pub name: String,
+    pub annotation: Option<Box<Expr>>,
+    pub default: Option<Box<Expr>>,
+    pub row: usize,
+    pub col: usize
+}
+
+#[derive(Debug)]
+pub struct Comprehension {
+    pub target: Expr,
+    pub iter: Expr,
+    pub ifs: Vec<Expr>,
+    pub is_async: bool
+}
+
+#[derive(Debug)]
+pub struct MatchCase {
+    pub pattern: Pattern,
+    pub guard: Option<Expr>,
+    pub body: Vec<Stmt>
+}
+
+#[derive(Debug)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub row: usize,
+    pub col: usize,
+    pub end_row: usize,
+    pub end_col: usize
+}
+
+#[derive(Debug)]
+pub enum PatternKind {
+    Value(Expr),
+    Singleton(Constant),
+    Sequence(Vec<Pattern>),
+    Mapping {
+        keys: Vec<Expr>,
+        patterns: Vec<Pattern>,
+        rest: Option<String>
+    },
+    Class {
+        cls: Expr,
+        patterns: Vec<Pattern>,
+        kwd_attrs: Vec<String>,
+        kwd_patterns: Vec<Pattern>
+    },
+    Star(Option<String>),
+    As {
+        pattern: Option<Box<Pattern>>,
+        name: Option<String>
+    },
+    Or(Vec<Pattern>)
+}
+
+#[derive(Debug)]
+pub struct WithItem {
+    pub context_expr: Expr,
+    pub optional_vars: Option<Expr>
+}
+
+#[derive(Debug)]
+pub struct Keyword {
+    pub arg: Option<String>,
+    pub value: Expr
+}
+
+#[derive(Debug)]
+pub enum TypeParam {
+    TypeVar {
+        name: String,
+        bound: Option<Box<Expr>>
+    },
+    TypeVarTuple(String),
+    ParamSpec(String)
+}
+
+// elementos, se havia virgula e os geradores de uma compreensao
+type TestList = (Vec<Expr>, bool, Option<Vec<Comprehension>>);
+
+fn error_at(code: ErrorCode, row: usize, col: usize, message: &str) -> CompilationError {
+    CompilationError::SintaxError(Box::new(Diagnostic::new(code, message, row, col, 1)))
+}
+
+fn error(code: ErrorCode, tk: &Token, message: &str) -> CompilationError {
+    CompilationError::SintaxError(Box::new(Diagnostic::new(code, message, tk.row, tk.col, tk.lexema.chars().count())))
+}
+
+fn too_deeply_nested(tk: &Token) -> CompilationError {
+    let diagnostic = Diagnostic::new(ErrorCode::TooDeeplyNested, "expression too deeply nested", tk.row, tk.col, 1);
+    CompilationError::SintaxError(Box::new(diagnostic))
+}
+
+// a marca do diagnostico fica numa linha so: um no que continua em outra linha marca so o inicio
+fn span_error(code: ErrorCode, (row, col): (usize, usize), (end_row, end_col): (usize, usize), message: &str) -> CompilationError {
+    let len = if end_row == row { end_col - col } else { 1 };
+    CompilationError::SintaxError(Box::new(Diagnostic::new(code, message, row, col, len)))
+}
+
+fn expr_error(code: ErrorCode, expr: &Expr, message: &str) -> CompilationError {
+    span_error(code, (expr.row, expr.col), end(expr), message)
+}
+
+fn pattern_error(code: ErrorCode, pattern: &Pattern, message: &str) -> CompilationError {
+    span_error(code, (pattern.row, pattern.col), (pattern.end_row, pattern.end_col), message)
+}
+
+// posicao logo depois do no, como o end_col_offset do CPython
+fn end(expr: &Expr) -> (usize, usize) {
+    (expr.end_row, expr.end_col)
+}
+
+fn token_end(tk: &Token) -> (usize, usize) {
+    (tk.row, tk.col + tk.lexema.chars().count())
+}
+
+fn operator(tk: &Token) -> Operator {
+    match tk.tk_type {
+        TkType::Operator("OPERATOR{MAIS}") | TkType::Operator("OPERATOR{MAIS_IGUAL}") => Operator::Add,
+        TkType::Operator("OPERATOR{MENOS}") | TkType::Operator("OPERATOR{MENOS_IGUAL}") => Operator::Sub,
+        TkType::Operator("OPERATOR{VEZES}") | TkType::Operator("OPERATOR{VEZES_IGUAL}") => Operator::Mult,
+        TkType::Operator("OPERATOR{BARRA}") | TkType::Operator("OPERATOR{BARRA_IGUAL}") => Operator::Div,
+        TkType::Operator("OPERATOR{ARROBA}") | TkType::Operator("OPERATOR{ARROBA_IGUAL}") => Operator::MatMult,
+        TkType::Operator("OPERATOR{PORCENTO}") | TkType::Operator("OPERATOR{PORCENTO_IGUAL}") => Operator::Mod,
+        TkType::Operator("OPERATOR{NOME_PARAMETRO}") | TkType::Operator("OPERATOR{DUPLO_ASTERISCO_IGUAL}") => Operator::Pow,
+        TkType::Operator("OPERATOR{SHIFT_LEFT}") | TkType::Operator("OPERATOR{SHIFT_LEFT_IGUAL}") => Operator::LShift,
+        TkType::Operator("OPERATOR{SHIFT_RIGHT}") | TkType::Operator("OPERATOR{SHIFT_RIGHT_IGUAL}") => Operator::RShift,
+        TkType::Operator("OPERATOR{PIPE}") | TkType::Operator("OPERATOR{PIPE_IGUAL}") => Operator::BitOr,
+        TkType::Operator("OPERATOR{CIRCUMFLEXO}") | TkType::Operator("OPERATOR{CIRCUMFLEXO_IGUAL}") => Operator::BitXor,
+        TkType::Operator("OPERATOR{ECOMERCIAL}") | TkType::Operator("OPERATOR{ECOMERCIAL_IGUAL}") => Operator::BitAnd,
+        TkType::Operator("OPERATOR{BARRA_DUPLA}") | TkType::Operator("OPERATOR{BARRA_DUPLA_IGUAL}") => Operator::FloorDiv,
+        _ => panic!("Unknown operator {:?}", tk.tk_type)
+    }
+}
+
+fn unary_operator(tk: &Token) -> UnaryOperator {
+    match tk.tk_type {
+        TkType::Operator("OPERATOR{TIL}") => UnaryOperator::Invert,
+        TkType::ReservedWord("RWORD{NOT}") => UnaryOperator::Not,
+        TkType::Operator("OPERATOR{MAIS}") => UnaryOperator::UAdd,
+        TkType::Operator("OPERATOR{MENOS}") => UnaryOperator::USub,
+        _ => panic!("Unknown unary operator {:?}", tk.tk_type)
+    }
+}
+
+fn cmp_operator(tk: &Token) -> CmpOperator {
+    match tk.tk_type {
+        TkType::Operator("OPERATOR{IGUAL_IGUAL}") => CmpOperator::Eq,
+        TkType::Operator("OPERATOR{DIFERENTE}") => CmpOperator::NotEq,
+        TkType::Operator("OPERATOR{MENOR}") => CmpOperator::Lt,
+        TkType::Operator("OPERATOR{MENOR_IGUAL}") => CmpOperator::LtE,
+        TkType::Operator("OPERATOR{MAIOR}") => CmpOperator::Gt,
+        TkType::Operator("OPERATOR{MAIOR_IGUAL}") => CmpOperator::GtE,
+        TkType::ReservedWord("RWORD{IN}") => CmpOperator::In,
+        TkType::ReservedWord("RWORD{NOT}") => CmpOperator::NotIn,
+        TkType::ReservedWord("RWORD{IS}") => CmpOperator::Is,
+        _ => panic!("Unknown comparison operator {:?}", tk.tk_type)
+    }
+}
+
+// nome usado nas mensagens "cannot assign to ..." e "cannot delete ..."
+fn describe(expr: &Expr) -> &'static str {
+    match expr.kind {
+        ExprKind::Constant(Constant::Bool(true)) => "True",
+        ExprKind::Constant(Constant::Bool(false)) => "False",
+        ExprKind::Constant(Constant::None) => "None",
+        ExprKind::Constant(Constant::Ellipsis) => "Ellipsis",
+        ExprKind::BoolOp { .. } | ExprKind::BinOp { .. } | ExprKind::UnaryOp { .. } => "operator",
+        ExprKind::NamedExpr { .. } => "named expression",
+        ExprKind::Lambda { .. } => "lambda",
+        ExprKind::IfExp { .. } => "conditional expression",
+        ExprKind::Await(_) => "await expression",
+        ExprKind::Yield(_) | ExprKind::YieldFrom(_) => "yield expression",
+        ExprKind::Compare { .. } => "comparison",
+        ExprKind::Call { .. } => "function call",
+        ExprKind::Constant(_) => "literal",
+        ExprKind::JoinedStr(_) => "f-string expression",
+        ExprKind::Dict { .. } => "dict display",
+        ExprKind::Set(_) => "set display",
+        ExprKind::ListComp { .. } => "list comprehension",
+        ExprKind::SetComp { .. } => "set comprehension",
+        ExprKind::DictComp { .. } => "dict comprehension",
+        ExprKind::GeneratorExp { .. } => "generator expression",
+        ExprKind::Starred(_) => "starred",
+        ExprKind::Name(_) => "name",
+        ExprKind::Attribute { .. } => "attribute",
+        ExprKind::Subscript { .. } => "subscript",
+        ExprKind::List(_) => "list",
+        ExprKind::Tuple(_) => "tuple",
+        ExprKind::Slice { .. } => "slice"
+    }
+}
+
+// o literal como o repr do python o mostraria, None se nao for um literal
+fn literal_repr(expr: &Expr) -> Option<String> {
+    match &expr.kind {
+        ExprKind::Constant(Constant::Int(s)) | ExprKind::Constant(Constant::Float(s)) | ExprKind::Constant(Constant::Imaginary(s)) => Some(s.clone()),
+        ExprKind::Constant(Constant::Str(s)) => {
+            // 'a' e "a" sao a mesma chave, b'a' nao
+            let prefix: String = s.chars().take_while(|c| c.is_ascii_alphabetic()).collect();
+            let body = &s[prefix.len() + 1..s.len() - 1];
+            let bytes = if prefix.to_lowercase().contains('b') { "b" } else { "" };
+
+            Some(format!("{}'{}'", bytes, body))
+        },
+        ExprKind::Constant(Constant::Bool(b)) => Some(if *b { "True" } else { "False" }.to_string()),
+        ExprKind::Constant(Constant::None) => Some("None".to_string()),
+        ExprKind::UnaryOp { op: UnaryOperator::USub, operand } => literal_repr(operand).map(|s| format!("-{}", s)),
+        ExprKind::BinOp { left, op, right } => {
+            let sign = if *op == Operator::Add { "+" } else { "-" };
+            Some(format!("({}{}{})", literal_repr(left)?, sign, literal_repr(right)?))
+        },
+        _ => None
+    }
+}
+
+fn is_f_string(tk: &Token) -> bool {
+    tk.lexema.chars().take_while(|c| c.is_ascii_alphabetic()).any(|c| c == 'f' || c == 'F')
+}
+
+fn check_deletable(expr: &Expr) -> Result<(), CompilationError> {
+    match &expr.kind {
+        ExprKind::Name(_) | ExprKind::Attribute { .. } | ExprKind::Subscript { .. } => Ok(()),
+        ExprKind::List(elts) | ExprKind::Tuple(elts) => {
+            for elt in elts {
+                check_deletable(elt)?;
+            }
+
+            Ok(())
+        },
+        _ => Err(expr_error(ErrorCode::InvalidTarget, expr, &format!("cannot delete {}", describe(expr))))
+    }
+}
+
+fn check_assignable(expr: &Expr) -> Result<(), CompilationError> {
+    match &expr.kind {
+        ExprKind::Name(_) | ExprKind::Attribute { .. } | ExprKind::Subscript { .. } => Ok(()),
+        ExprKind::Starred(value) => check_assignable(value),
+        ExprKind::List(elts) | ExprKind::Tuple(elts) => {
+            let mut starred = elts.iter().filter(|elt| matches!(elt.kind, ExprKind::Starred(_)));
+
+            if let (Some(_), Some(second)) = (starred.next(), starred.next()) {
+                return Err(expr_error(ErrorCode::InvalidTarget, second, "multiple starred expressions in assignment"));
+            }
+
+            for elt in elts {
+                check_assignable(elt)?;
+            }
+
+            Ok(())
+        },
+        _ => Err(expr_error(ErrorCode::InvalidTarget, expr, &format!("cannot assign to {}", describe(expr))))
+    }
+}
+
+// alvo completo de uma atribuicao, for ou with
+fn check_target(expr: &Expr) -> Result<(), CompilationError> {
+    if let ExprKind::Starred(_) = expr.kind {
+        return Err(expr_error(ErrorCode::InvalidTarget, expr, "starred assignment target must be in a list or tuple"));
+    }
+
+    check_assignable(expr)
+}
+
+// valor usado sozinho, fora de uma tupla
+fn check_value(expr: Expr) -> Result<Expr, CompilationError> {
+    match expr.kind {
+        ExprKind::Starred(_) => Err(expr_error(ErrorCode::InvalidUnpacking, &expr, "can't use starred expression here")),
+        _ => Ok(expr)
+    }
+}
+
+// um padrao que sempre casa so pode ser o ultimo
+fn check_reachable(pattern: &Pattern) -> Result<(), CompilationError> {
+    let capture = match &pattern.kind {
+        PatternKind::As { pattern: None, name } => name,
+        PatternKind::As { pattern: Some(inner), .. } => return check_reachable(inner),
+        PatternKind::Or(alternatives) => {
+            for alternative in alternatives {
+                check_reachable(alternative)?;
+            }
+
+            return Ok(());
+        },
+        _ => return Ok(())
+    };
+
+    let message = match capture {
+        Some(name) => format!("name capture '{}' makes remaining patterns unreachable", name),
+        None => "wildcard makes remaining patterns unreachable".to_string()
+    };
+
+    Err(pattern_error(ErrorCode::InvalidPattern, pattern, &message))
+}
+
+// nomes capturados por um padrao, cada um no maximo uma vez
+fn bound_names(pattern: &Pattern, names: &mut Vec<String>) -> Result<(), CompilationError> {
+    let mut bind = |name: &Option<String>| match name {
+        Some(name) if names.contains(name) => {
+            Err(pattern_error(ErrorCode::InvalidPattern, pattern, &format!("multiple assignments to name '{}' in pattern", name)))
+        },
+        Some(name) => {
+            names.push(name.clone());
+            Ok(())
+        },
+        None => Ok(())
+    };
+
+    match &pattern.kind {
+        PatternKind::Star(name) => bind(name),
+        PatternKind::As { pattern: inner, name } => {
+            bind(name)?;
+
+            match inner {
+                Some(inner) => bound_names(inner, names),
+                None => Ok(())
+            }
+        },
+        PatternKind::Mapping { patterns, rest, .. } => {
+            bind(rest)?;
+
+            for pattern in patterns {
+                bound_names(pattern, names)?;
+            }
+
+            Ok(())
+        },
+        PatternKind::Sequence(patterns) | PatternKind::Class { patterns, .. } => {
+            for pattern in patterns {
+                bound_names(pattern, names)?;
+            }
+
+            if let PatternKind::Class { kwd_patterns, .. } = &pattern.kind {
+                for pattern in kwd_patterns {
+                    bound_names(pattern, names)?;
+                }
+            }
+
+            Ok(())
+        },
+        // todas as alternativas ligam os mesmos nomes, basta a primeira
+        PatternKind::Or(alternatives) => bound_names(&alternatives[0], names),
+        PatternKind::Value(_) | PatternKind::Singleton(_) => Ok(())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ScopeKind {
+    Module,
+    Class,
+    Function
+}
+
+// nomes lidos e atribuidos num escopo
+#[derive(Default)]
+struct Names<'m> {
+    used: HashSet<&'m str>,
+    assigned: HashSet<&'m str>
+}
+
+// o que uma funcao em volta liga no corpo inteiro, para resolver os nonlocal de dentro
+struct Bindings<'m> {
+    bound: HashSet<&'m str>,
+    globals: HashSet<&'m str>
+}
+
+struct Scope<'m, 's> {
+    kind: ScopeKind,
+    // funcoes em volta deste escopo (sem contar ele mesmo), da mais de fora para a mais de dentro
+    enclosing: Vec<&'s Bindings<'m>>,
+    // o que este escopo liga, se for uma funcao
+    own: Option<&'s Bindings<'m>>,
+    parameters: HashSet<&'m str>,
+    globals: HashSet<&'m str>,
+    nonlocals: HashSet<&'m str>,
+    // o que ja apareceu antes do statement atual
+    seen: Names<'m>
+}
+
+impl<'m, 's> Scope<'m, 's> {
+    fn new(kind: ScopeKind, enclosing: Vec<&'s Bindings<'m>>, own: Option<&'s Bindings<'m>>, parameters: HashSet<&'m str>) -> Scope<'m, 's> {
+        Scope {
+            kind,
+            enclosing,
+            own,
+            parameters,
+            globals: HashSet::new(),
+            nonlocals: HashSet::new(),
+            seen: Names::default()
+        }
+    }
+
+    // funcoes em volta de um escopo definido dentro deste
+    fn nested(&self) -> Vec<&'s Bindings<'m>> {
+        self.enclosing.iter().copied().chain(self.own).collect()
+    }
+
+    // a funcao mais proxima que liga o nome, parando numa que o declara global
+    fn resolves(&self, name: &str) -> bool {
+        for bindings in self.enclosing.iter().rev() {
+            if bindings.globals.contains(name) {
+                return false;
+            }
+
+            if bindings.bound.contains(name) {
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+fn parameter_names(args: &Arguments) -> HashSet<&str> {
+    args.posonlyargs.iter()
+        .chain(args.args.iter())
+        .chain(args.vararg.iter())
+        .chain(args.kwonlyargs.iter())
+        .chain(args.kwarg.iter())
+        .map(|arg| arg.name.as_str())
+        .collect()
+}
+
+// lambda e compreensao tem escopo proprio: o que e lido dentro delas nao conta, so o := de uma
+// compreensao atribui no escopo de fora. O primeiro iteravel de uma compreensao e lido de fora
+fn expression_names<'m>(expr: &'m Expr, outer: bool, names: &mut Names<'m>) {
+    let visit = |e: &'m Expr, names: &mut Names<'m>| expression_names(e, outer, names);
+
+    match &expr.kind {
+        ExprKind::Name(name) => {
+            if outer {
+                names.used.insert(name);
+            }
+        },
+        ExprKind::NamedExpr { target, value } => {
+            if let ExprKind::Name(name) = &target.kind {
+                names.assigned.insert(name);
+            }
+
+            visit(value, names);
+        },
+        ExprKind::BoolOp { values, .. } => values.iter().for_each(|e| visit(e, names)),
+        ExprKind::BinOp { left, right, .. } => {
+            visit(left, names);
+            visit(right, names);
+        },
+        ExprKind::UnaryOp { operand: value, .. } |
+        ExprKind::Await(value) |
+        ExprKind::YieldFrom(value) |
+        ExprKind::Starred(value) |
+        ExprKind::Attribute { value, .. } => visit(value, names),
+        ExprKind::Yield(value) => value.iter().for_each(|v| visit(v, names)),
+        ExprKind::Lambda { args, .. } => {
+            for arg in args.posonlyargs.iter().chain(args.args.iter()).chain(args.kwonlyargs.iter()) {
+                arg.default.iter().for_each(|d| visit(d, names));
+            }
+        },
+        ExprKind::IfExp { test, body, orelse } => {
+            visit(test, names);
+            visit(body, names);
+            visit(orelse, names);
+        },
+        ExprKind::Compare { left, comparators, .. } => {
+            visit(left, names);
+            comparators.iter().for_each(|e| visit(e, names));
+        },
+        ExprKind::Call { func, args, keywords } => {
+            visit(func, names);
+            args.iter().for_each(|e| visit(e, names));
+            keywords.iter().for_each(|k| visit(&k.value, names));
+        },
+        ExprKind::Subscript { value, slice } => {
+            visit(value, names);
+            visit(slice, names);
+        },
+        ExprKind::Dict { keys, values } => {
+            keys.iter().flatten().for_each(|e| visit(e, names));
+            values.iter().for_each(|e| visit(e, names));
+        },
+        ExprKind::Set(elts) | ExprKind::List(elts) | ExprKind::Tuple(elts) => elts.iter().for_each(|e| visit(e, names)),
+        ExprKind::ListComp { elt, generators } | ExprKind::SetComp { elt, generators } | ExprKind::GeneratorExp { elt, generators } => {
+            comprehension_names(&[elt], generators, outer, names);
+        },
+        ExprKind::DictComp { key, value, generators } => comprehension_names(&[key, value], generators, outer, names),
+        ExprKind::Slice { lower, upper, step } => {
+            lower.iter().chain(upper.iter()).chain(step.iter()).for_each(|part| visit(part, names));
+        },
+        ExprKind::Constant(_) | ExprKind::JoinedStr(_) => {}
+    }
+}
+
+fn comprehension_names<'m>(elts: &[&'m Expr], generators: &'m [Comprehension], outer: bool, names: &mut Names<'m>) {
+    for (k, generator) in generators.iter().enumerate() {
+        expression_names(&generator.iter, outer && k == 0, names);
+        generator.ifs.iter().for_each(|e| expression_names(e, false, names));
+    }
+
+    elts.iter().for_each(|e| expression_names(e, false, names));
+}
+
+// a propria expressao ou a primeira subexpressao em que found vale. O corpo de uma lambda tem
+// escopo proprio e fica de fora, os valores padrao dos parametros nao
+fn find<'e>(expr: &'e Expr, found: &dyn Fn(&Expr) -> bool) -> Option<&'e Expr> {
+    if found(expr) {
+        return Some(expr);
+    }
+
+    let mut children: Vec<&Expr> = Vec::new();
+
+    match &expr.kind {
+        ExprKind::BoolOp { values: elts, .. } |
+        ExprKind::Set(elts) |
+        ExprKind::List(elts) |
+        ExprKind::Tuple(elts) => children.extend(elts),
+        ExprKind::NamedExpr { target: left, value: right } |
+        ExprKind::BinOp { left, right, .. } |
+        ExprKind::Subscript { value: left, slice: right } => children.extend([&**left, &**right]),
+        ExprKind::UnaryOp { operand: value, .. } |
+        ExprKind::Await(value) |
+        ExprKind::YieldFrom(value) |
+        ExprKind::Starred(value) |
+        ExprKind::Attribute { value, .. } => children.push(value),
+        ExprKind::Yield(value) => children.extend(value.as_deref()),
+        ExprKind::Lambda { args, .. } => {
+            let parameters = args.posonlyargs.iter().chain(args.args.iter()).chain(args.kwonlyargs.iter());
+            children.extend(parameters.filter_map(|arg| arg.default.as_deref()));
+        },
+        ExprKind::IfExp { test, body, orelse } => children.extend([&**test, &**body, &**orelse]),
+        ExprKind::Compare { left, comparators, .. } => {
+            children.push(left);
+            children.extend(comparators);
+        },
+        ExprKind::Call { func, args, keywords } => {
+            children.push(func);
+            children.extend(args);
+            children.extend(keywords.iter().map(|k| &k.value));
+        },
+        ExprKind::Dict { keys, values } => {
+            children.extend(keys.iter().flatten());
+            children.extend(values);
+        },
+        ExprKind::ListComp { elt, generators } | ExprKind::SetComp { elt, generators } | ExprKind::GeneratorExp { elt, generators } => {
+            children.push(elt);
+            generators.iter().for_each(|g| children.extend(once(&g.target).chain(once(&g.iter)).chain(g.ifs.iter())));
+        },
+        ExprKind::DictComp { key, value, generators } => {
+            children.extend([&**key, &**value]);
+            generators.iter().for_each(|g| children.extend(once(&g.target).chain(once(&g.iter)).chain(g.ifs.iter())));
+        },
+        ExprKind::Slice { lower, upper, step } => children.extend(lower.iter().chain(upper.iter()).chain(step.iter()).map(|e| &**e)),
+        ExprKind::Name(_) | ExprKind::Constant(_) | ExprKind::JoinedStr(_) => {}
+    }
+
+    children.into_iter().find_map(|e| find(e, found))
+}
+
+// o nome atribuido por um :=
+fn named_target(expr: &Expr) -> Option<&str> {
+    match &expr.kind {
+        ExprKind::NamedExpr { target, .. } => match &target.kind {
+            ExprKind::Name(name) => Some(name),
+            _ => None
+        },
+        _ => None
+    }
+}
+
+// o elemento, as condicoes e os iteraveis internos de uma compreensao rodam no escopo dela, so o
+// primeiro iteravel e avaliado fora
+fn comprehension_scope(comprehension: &Expr) -> Option<(Vec<&Expr>, &[Comprehension])> {
+    let (elts, generators) = match &comprehension.kind {
+        ExprKind::ListComp { elt, generators } | ExprKind::SetComp { elt, generators } | ExprKind::GeneratorExp { elt, generators } => {
+            (vec![&**elt], generators)
+        },
+        ExprKind::DictComp { key, value, generators } => (vec![&**key, &**value], generators),
+        _ => return None
+    };
+
+    let mut inner = elts;
+    inner.extend(generators.iter().flat_map(|g| g.ifs.iter()));
+    inner.extend(generators.iter().skip(1).map(|g| &g.iter));
+
+    Some((inner, generators))
+}
+
+// x = ...: o nome e atribuido, mas a.b = ... e a[i] = ... leem a e i
+fn target_names<'m>(expr: &'m Expr, names: &mut Names<'m>) {
+    match &expr.kind {
+        ExprKind::Name(name) => {
+            names.assigned.insert(name);
+        },
+        ExprKind::Starred(value) => target_names(value, names),
+        ExprKind::List(elts) | ExprKind::Tuple(elts) => elts.iter().for_each(|elt| target_names(elt, names)),
+        _ => expression_names(expr, true, names)
+    }
+}
+
+fn pattern_names<'m>(pattern: &'m Pattern, names: &mut Names<'m>) {
+    let mut bind = |name: &'m Option<String>| {
+        if let Some(name) = name {
+            names.assigned.insert(name);
+        }
+    };
+
+    match &pattern.kind {
+        PatternKind::Star(name) => bind(name),
+        PatternKind::As { pattern: inner, name } => {
+            bind(name);
+            inner.iter().for_each(|p| pattern_names(p, names));
+        },
+        PatternKind::Mapping { patterns, rest, .. } => {
+            bind(rest);
+            patterns.iter().for_each(|p| pattern_names(p, names));
+        },
+        PatternKind::Class { patterns, kwd_patterns, .. } => {
+            patterns.iter().chain(kwd_patterns.iter()).for_each(|p| pattern_names(p, names));
+        },
+        PatternKind::Sequence(patterns) | PatternKind::Or(patterns) => patterns.iter().for_each(|p| pattern_names(p, names)),
+        PatternKind::Value(_) | PatternKind::Singleton(_) => {}
+    }
+}
+
+// o que o proprio statement le e atribui, sem entrar nos blocos
+fn statement_names<'m>(stmt: &'m Stmt, names: &mut Names<'m>) {
+    let mut read = |e: &'m Expr| expression_names(e, true, names);
+
+    match &stmt.kind {
+        StmtKind::FunctionDef { name, args, decorator_list, returns, .. } => {
+            decorator_list.iter().for_each(&mut read);
+
+            for arg in args.posonlyargs.iter().chain(args.args.iter()).chain(args.vararg.iter()).chain(args.kwonlyargs.iter()).chain(args.kwarg.iter()) {
+                arg.default.iter().chain(arg.annotation.iter()).for_each(|e| read(e));
+            }
+
+            returns.iter().for_each(read);
+            names.assigned.insert(name);
+        },
+        StmtKind::ClassDef { name, bases, keywords, decorator_list, .. } => {
+            decorator_list.iter().chain(bases.iter()).for_each(&mut read);
+            keywords.iter().for_each(|k| read(&k.value));
+            names.assigned.insert(name);
+        },
+        StmtKind::Assign { targets, value } => {
+            read(value);
+            targets.iter().for_each(|t| target_names(t, names));
+        },
+        StmtKind::AugAssign { target, value, .. } => {
+            read(value);
+            target_names(target, names);
+        },
+        StmtKind::AnnAssign { target, annotation, value, .. } => {
+            read(annotation);
+            value.iter().for_each(read);
+            target_names(target, names);
+        },
+        StmtKind::For { target, iter, .. } => {
+            read(iter);
+            target_names(target, names);
+        },
+        StmtKind::While { test, .. } | StmtKind::If { test, .. } => read(test),
+        StmtKind::With { items, .. } => {
+            for item in items {
+                expression_names(&item.context_expr, true, names);
+                item.optional_vars.iter().for_each(|t| target_names(t, names));
+            }
+        },
+        StmtKind::Match { subject, cases } => {
+            read(subject);
+
+            for case in cases {
+                pattern_names(&case.pattern, names);
+                case.guard.iter().for_each(|g| expression_names(g, true, names));
+            }
+        },
+        // o valor de um type so e avaliado quando usado, no escopo do proprio alias
+        StmtKind::TypeAlias { name, .. } => target_names(name, names),
+        StmtKind::Delete(targets) => targets.iter().for_each(|t| target_names(t, names)),
+        StmtKind::Return(value) => value.iter().for_each(read),
+        StmtKind::Assert { test, msg } => {
+            read(test);
+            msg.iter().for_each(read);
+        },
+        StmtKind::Print { dest, values, .. } => dest.iter().chain(values.iter()).for_each(read),
+        StmtKind::Exec { body, globals, locals } => {
+            read(body);
+            globals.iter().chain(locals.iter()).for_each(read);
+        },
+        StmtKind::Expr(value) => read(value),
+        StmtKind::Global(_) | StmtKind::Nonlocal(_) | StmtKind::Pass | StmtKind::Break | StmtKind::Continue => {}
+    }
+}
+
+// blocos que pertencem ao mesmo escopo do statement
+fn blocks(stmt: &Stmt) -> Vec<&[Stmt]> {
+    match &stmt.kind {
+        StmtKind::For { body, orelse, .. } | StmtKind::While { body, orelse, .. } | StmtKind::If { body, orelse, .. } => vec![body, orelse],
+        StmtKind::With { body, .. } => vec![body],
+        StmtKind::Match { cases, .. } => cases.iter().map(|case| case.body.as_slice()).collect(),
+        _ => Vec::new()
+    }
+}
+
+// tudo que o corpo de uma funcao liga, em qualquer ponto dele
+fn bindings<'m>(body: &'m [Stmt], bindings: &mut Bindings<'m>) {
+    for stmt in body {
+        match &stmt.kind {
+            StmtKind::Global(names) => bindings.globals.extend(names.iter().map(|n| n.as_str())),
+            _ => {
+                let mut names = Names::default();
+                statement_names(stmt, &mut names);
+                bindings.bound.extend(names.assigned);
+            }
+        }
+
+        for block in blocks(stmt) {
+            self::bindings(block, bindings);
+        }
+    }
+}
+
+fn check_declarations<'m>(body: &'m [Stmt], scope: &mut Scope<'m, '_>) -> Result<(), CompilationError> {
+    let stmt_error = |stmt: &Stmt, message: String| error_at(ErrorCode::InvalidDeclaration, stmt.row, stmt.col, &message);
+    // a ordem das verificacoes e a do CPython
+    let seen_before = |scope: &Scope, name: &str, declaration: &str| {
+        if scope.seen.assigned.contains(name) {
+            Some(format!("name '{}' is assigned to before {} declaration", name, declaration))
+        } else if scope.seen.used.contains(name) {
+            Some(format!("name '{}' is used prior to {} declaration", name, declaration))
+        } else {
+            None
+        }
+    };
+
+    for stmt in body {
+        match &stmt.kind {
+            StmtKind::Global(names) => {
+                for name in names {
+                    if scope.parameters.contains(name.as_str()) {
+                        return Err(stmt_error(stmt, format!("name '{}' is parameter and global", name)));
+                    }
+
+                    if scope.nonlocals.contains(name.as_str()) {
+                        return Err(stmt_error(stmt, format!("name '{}' is nonlocal and global", name)));
+                    }
+
+                    if let Some(message) = seen_before(scope, name, "global") {
+                        return Err(stmt_error(stmt, message));
+                    }
+
+                    scope.globals.insert(name);
+                }
+            },
+            StmtKind::Nonlocal(names) => {
+                if scope.kind != ScopeKind::Function && scope.enclosing.is_empty() {
+                    return Err(stmt_error(stmt, "nonlocal declaration not allowed at module level".to_string()));
+                }
+
+                for name in names {
+                    if scope.parameters.contains(name.as_str()) {
+                        return Err(stmt_error(stmt, format!("name '{}' is parameter and nonlocal", name)));
+                    }
+
+                    if scope.globals.contains(name.as_str()) {
+                        return Err(stmt_error(stmt, format!("name '{}' is nonlocal and global", name)));
+                    }
+
+                    if let Some(message) = seen_before(scope, name, "nonlocal") {
+                        return Err(stmt_error(stmt, message));
+                    }
+
+                    if !scope.resolves(name) {
+                        return Err(stmt_error(stmt, format!("no binding for nonlocal '{}' found", name)));
+                    }
+
+                    scope.nonlocals.insert(name);
+                }
+            },
+            _ => statement_names(stmt, &mut scope.seen)
+        }
+
+        match &stmt.kind {
+            StmtKind::FunctionDef { args, body, .. } => {
+                let mut own = Bindings {
+                    bound: parameter_names(args),
+                    globals: HashSet::new()
+                };
+                bindings(body, &mut own);
+
+                check_declarations(body, &mut Scope::new(ScopeKind::Function, scope.nested(), Some(&own), parameter_names(args)))?;
+            },
+            StmtKind::ClassDef { body, .. } => {
+                check_declarations(body, &mut Scope::new(ScopeKind::Class, scope.nested(), None, HashSet::new()))?;
+            },
+            _ => {
+                for block in blocks(stmt) {
+                    check_declarations(block, scope)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub fn lower(tree: &ParseTree) -> Result<Vec<Stmt>, CompilationError> {
+    let lowering = Lowering {
+        tree,
+        frames: RefCell::new(Vec::new()),
+        depth: Cell::new(0),
+        chain_depth: Cell::new(0)
+    };
+    // FILE -> STATEMENT_LIST_E
+    let module = lowering.statement_list(tree.children(tree.root)[0])?;
+
+    check_declarations(&module, &mut Scope::new(ScopeKind::Module, Vec::new(), None, HashSet::new()))?;
+
+    Ok(module)
+}
+
+// escopos de funcao e classe abertos durante a conversao
+enum Frame {
+    Class,
+    Function {
+        is_async: bool,
+        is_generator: bool,
+        // primeiro return com valor, proibido em geradores assincronos
+        return_value: Option<(usize, usize)>
+    }
+}
+
+impl Frame {
+    fn function(is_async: bool) -> Frame {
+        Frame::Function {
+            is_async,
+            is_generator: false,
+            return_value: None
+        }
+    }
+}
+
+// alem disso a expressao vira erro em vez de estourar a pilha: MAX_NESTING chamadas recursivas
+// na conversao e MAX_DEPTH niveis na arvore final, onde a + b + c tambem aninha
+const MAX_NESTING: usize = 500;
+const MAX_DEPTH: usize = 5000;
+
+struct Lowering<'t, 'a> {
+    tree: &'t ParseTree<'a>,
+    frames: RefCell<Vec<Frame>>,
+    depth: Cell<usize>,
+    // niveis a mais das cadeias a + b + c e a.b.c, que sao convertidas num laco
+    chain_depth: Cell<usize>
+}
+
+impl<'t, 'a> Lowering<'t, 'a> {
+    fn terminal(&self, node: usize) -> Option<TkType> {
+        match self.tree.state(node) {
+            PossibleStates::Terminal(tk_type) => Some(tk_type),
+            _ => None
+        }
+    }
+
+    fn expr(&self, kind: ExprKind, tk: &Token) -> Expr {
+        self.expr_to(kind, tk, token_end(tk))
+    }
+
+    // comeca no token, como em not x ou [x], e termina em outro ponto
+    fn expr_to(&self, kind: ExprKind, tk: &Token, (end_row, end_col): (usize, usize)) -> Expr {
+        Expr {
+            kind,
+            row: tk.row,
+            col: tk.col,
+            end_row,
+            end_col
+        }
+    }
+
+    fn end_of(&self, node: usize) -> (usize, usize) {
+        token_end(self.tree.last_token(node).unwrap())
+    }
+
+    fn in_frame<T>(
+        &self,
+        frame: Frame,
+        lower: &dyn Fn() -> Result<T, CompilationError>
+    ) -> Result<(T, Frame), CompilationError> {
+        self.frames.borrow_mut().push(frame);
+        let result = lower();
+        let frame = self.frames.borrow_mut().pop().unwrap();
+
+        Ok((result?, frame))
+    }
+
+    // await, async for e async with so valem dentro de uma funcao async
+    fn check_async(&self, tk: &Token, what: &str) -> Result<(), CompilationError> {
+        match self.frames.borrow().last() {
+            Some(Frame::Function { is_async: true, .. }) => Ok(()),
+            None if what == "await" => Err(error(ErrorCode::InvalidContext, tk, "'await' outside function")),
+            _ => Err(error(ErrorCode::InvalidContext, tk, &format!("'{}' outside async function", what)))
+        }
+    }
+
+    // so a expressao geradora pode ter async for fora de uma funcao async, ela vira um gerador async
+    fn check_async_comprehension(&self, tk: &Token, generators: &[Comprehension]) -> Result<(), CompilationError> {
+        match self.frames.borrow().last() {
+            Some(Frame::Function { is_async: true, .. }) => Ok(()),
+            _ if generators.iter().any(|g| g.is_async) => {
+                Err(error(ErrorCode::InvalidContext, tk, "asynchronous comprehension outside of an asynchronous function"))
+            },
+            _ => Ok(())
+        }
+    }
+
+    // o := de uma compreensao atribui no escopo de fora, que nao pode ser uma classe nem a propria
+    // compreensao; yield tambem nao vale no escopo dela
+    fn check_comprehension(&self, comprehension: &Expr) -> Result<(), CompilationError> {
+        let (inner, generators) = match comprehension_scope(comprehension) {
+            Some(scope) => scope,
+            None => return Ok(())
+        };
+
+        let is_named = |e: &Expr| matches!(e.kind, ExprKind::NamedExpr { .. });
+
+        if let Some(named) = generators.iter().find_map(|g| find(&g.iter, &is_named)) {
+            return Err(expr_error(ErrorCode::InvalidContext, named, "assignment expression cannot be used in a comprehension iterable expression"));
+        }
+
+        let mut variables = Names::default();
+        generators.iter().for_each(|g| target_names(&g.target, &mut variables));
+
+        let rebinds = |e: &Expr| named_target(e).is_some_and(|name| variables.assigned.contains(name));
+
+        if let Some(named) = inner.iter().find_map(|e| find(e, &rebinds)) {
+            let message = format!("assignment expression cannot rebind comprehension iteration variable '{}'", named_target(named).unwrap_or_default());
+            return Err(expr_error(ErrorCode::InvalidTarget, named, &message));
+        }
+
+        if let Some(Frame::Class) = self.frames.borrow().last() {
+            if let Some(named) = inner.iter().find_map(|e| find(e, &is_named)) {
+                return Err(expr_error(ErrorCode::InvalidContext, named, "assignment expression within a comprehension cannot be used in a class body"));
+            }
+        }
+
+        let is_yield = |e: &Expr| matches!(e.kind, ExprKind::Yield(_) | ExprKind::YieldFrom(_));
+
+        if let Some(value) = inner.iter().find_map(|e| find(e, &is_yield)) {
+            return Err(expr_error(ErrorCode::InvalidContext, value, &format!("'yield' inside {}", describe(comprehension))));
+        }
+
+        Ok(())
+    }
+
+    fn name(&self, node: usize) -> Expr {
+        let tk = self.tree.token(node);
+        self.expr(ExprKind::Name(tk.lexema.clone()), tk)
+    }
+
+    // X -> Y XL; XL -> op X | e
+    fn tail(&self, node: usize) -> Option<(&'a Token, usize)> {
+        let children = self.tree.children(node);
+
+        if children.is_empty() {
+            None
+        } else {
+            Some((self.tree.token(children[0]), children[1]))
+        }
+    }
+
+    // X -> Y XL; XL -> op X | e, lido como Y (op Y)*
+    fn chain(&self, node: usize) -> (usize, Vec<(usize, usize)>) {
+        let c = self.tree.children(node);
+        let mut rest = Vec::new();
+        let mut tail = c[1];
+
+        loop {
+            let t = self.tree.children(tail);
+
+            if t.is_empty() {
+                break;
+            }
+
+            let next = self.tree.children(t[1]);
+            rest.push((t[0], next[0]));
+            tail = next[1];
+        }
+
+        (c[0], rest)
+    }
+
+    fn statement_list(&self, node: usize) -> Result<Vec<Stmt>, CompilationError> {
+        let mut body = Vec::new();
+        let mut node = node;
+
+        loop {
+            let children = self.tree.children(node);
+
+            if children.is_empty() {
+                break;
+            }
+
+            let statement = self.tree.children(children[0]);
+
+            if self.tree.state(statement[0]) == PossibleStates::SIMPLE_STATEMENTS {
+                body.extend(self.simple_statements(statement[0])?);
+            } else {
+                body.push(self.statement(children[0])?);
+            }
+
+            node = children[1];
+        }
+
+        Ok(body)
+    }
+
+    // SIMPLE_STATEMENTS -> SIMPLE_STATEMENT SIMPLE_STATEMENTS_D eos, separados por ;
+    fn simple_statements(&self, node: usize) -> Result<Vec<Stmt>, CompilationError> {
+        Ok(self.comma_list(node, &|n| self.statement(n))?.0)
+    }
+
+    // SCOPE -> eos INDENT STATEMENT_LIST DEDENT | SIMPLE_STATEMENTS
+    fn scope(&self, node: usize) -> Result<Vec<Stmt>, CompilationError> {
+        match *self.tree.children(node) {
+            [simple_statements] => self.simple_statements(simple_statements),
+            ref c => self.statement_list(c[2])
+        }
+    }
+
+    fn statement(&self, node: usize) -> Result<Stmt, CompilationError> {
+        let c = self.tree.children(node);
+
+        let kind = match self.tree.state(c[0]) {
+            PossibleStates::EXPRESSION_STATEMENT => return self.expression_statement(c[0]),
+            PossibleStates::FUNCTION_DEF | PossibleStates::CLASS_DEF => return self.definition(c[0], Vec::new(), false),
+            PossibleStates::FOR_STATEMENT => return self.for_statement(c[0], false),
+            PossibleStates::WITH_STATEMENT => return self.with_statement(c[0], false),
+            PossibleStates::MATCH_STATEMENT => return self.match_statement(c[0]),
+            PossibleStates::DECORATORS => {
+                let mut decorator_list = Vec::new();
+                let mut node = c[0];
+
+                while let Some(&decorator) = self.tree.children(node).first() {
+                    decorator_list.push(self.expression(self.tree.children(decorator)[1])?);
+                    node = self.tree.children(node)[1];
+                }
+
+                return match self.tree.children(c[1]) {
+                    &[definition] => self.definition(definition, decorator_list, false),
+                    d => self.async_statement(d, decorator_list)
+                };
+            },
+            PossibleStates::Terminal(TkType::ReservedWord("RWORD{ASYNC}")) => return self.async_statement(c, Vec::new()),
+            PossibleStates::Terminal(TkType::ReservedWord("RWORD{BREAK}")) => StmtKind::Break,
+            PossibleStates::Terminal(TkType::ReservedWord("RWORD{CONTINUE}")) => StmtKind::Continue,
+            PossibleStates::Terminal(TkType::ReservedWord("RWORD{RETURN}")) => match self.tree.children(c[1]).first() {
+                Some(&value) => {
+                    if let Some(Frame::Function { return_value: return_value @ None, .. }) = self.frames.borrow_mut().last_mut() {
+                        let tk = self.tree.token(c[0]);
+                        *return_value = Some((tk.row, tk.col));
+                    }
+
+                    StmtKind::Return(Some(self.expression_list(value)?))
+                },
+                None => StmtKind::Return(None)
+            },
+            PossibleStates::Terminal(TkType::ReservedWord("RWORD{PASS}")) => StmtKind::Pass,
+            PossibleStates::Terminal(TkType::ReservedWord("RWORD{DEL}")) => {
+                let (targets, _) = self.comma_list(c[1], &|n| self.star_or_expression(n))?;
+
+                for target in &targets {
+                    check_deletable(target)?;
+                }
+
+                StmtKind::Delete(targets)
+            },
+            PossibleStates::Terminal(TkType::ReservedWord("RWORD{GLOBAL}")) => StmtKind::Global(self.id_list(c[1])),
+            PossibleStates::Terminal(TkType::ReservedWord("RWORD{NONLOCAL}")) => StmtKind::Nonlocal(self.id_list(c[1])),
+            PossibleStates::Terminal(TkType::ReservedWord("RWORD{ASSERT}")) => StmtKind::Assert {
+                test: self.expression(c[1])?,
+                msg: match self.tree.children(c[2]).get(1) {
+                    Some(&msg) => Some(self.expression(msg)?),
+                    None => None
+                }
+            },
+            PossibleStates::Terminal(TkType::ReservedWord("RWORD{PRINT}")) => self.print_statement(c[1])?,
+            PossibleStates::Terminal(TkType::ReservedWord("RWORD{EXEC}")) => {
+                let namespace = self.tree.children(c[2]);
+
+                StmtKind::Exec {
+                    body: self.expression(c[1])?,
+                    globals: match namespace.get(1) {
+                        Some(&globals) => Some(self.expression(globals)?),
+                        None => None
+                    },
+                    locals: match namespace.get(2).and_then(|&l| self.tree.children(l).get(1)) {
+                        Some(&locals) => Some(self.expression(locals)?),
+                        None => None
+                    }
+                }
+            },
+            PossibleStates::Terminal(TkType::ReservedWord("RWORD{TYPE}")) => StmtKind::TypeAlias {
+                name: self.name(c[1]),
+                type_params: self.type_params(c[2])?,
+                value: self.expression(c[4])?
+            },
+            PossibleStates::Terminal(TkType::ReservedWord("RWORD{WHILE}")) => StmtKind::While {
+                test: self.expression(c[1])?,
+                body: self.scope(c[3])?,
+                orelse: self.loop_else(c[4])?
+            },
+            PossibleStates::Terminal(TkType::ReservedWord("RWORD{IF}")) => StmtKind::If {
+                test: self.expression(c[1])?,
+                body: self.scope(c[3])?,
+                orelse: self.else_statement(c[4])?
+            },
+            state => panic!("Unexpected statement {:?}", state)
+        };
+
+        let tk = self.tree.token(c[0]);
+
+        Ok(Stmt {
+            kind,
+            row: tk.row,
+            col: tk.col
+        })
+    }
+
+    // TYPE_PARAMS_E -> [ TYPE_PARAM_LIST ] | e; TYPE_PARAM_LIST -> TYPE_PARAM TYPE_PARAM_LIST_D
+    // TYPE_PARAM -> id TYPE_BOUND_E | * id | ** id
+    // [T, T: limite, *Ts, **P]
+    fn type_params(&self, node: usize) -> Result<Vec<TypeParam>, CompilationError> {
+        let list = match self.tree.children(node).get(1) {
+            Some(&list) => list,
+            None => return Ok(Vec::new())
+        };
+
+        let (params, _) = self.comma_list(list, &|n| {
+            let c = self.tree.children(n);
+            let name = match self.tree.state(c[0]) {
+                PossibleStates::Terminal(TkType::Identifier) => self.tree.token(c[0]),
+                _ => self.tree.token(c[1])
+            };
+
+            let param = match self.tree.token(c[0]).lexema.as_str() {
+                "*" => TypeParam::TypeVarTuple(name.lexema.clone()),
+                "**" => TypeParam::ParamSpec(name.lexema.clone()),
+                _ => TypeParam::TypeVar {
+                    name: name.lexema.clone(),
+                    bound: match self.tree.children(c[1]).get(1) {
+                        Some(&bound) => Some(Box::new(self.expression(bound)?)),
+                        None => None
+                    }
+                }
+            };
+
+            Ok((param, name))
+        })?;
+
+        let mut names = HashSet::new();
+
+        for (_, name) in &params {
+            if !names.insert(&name.lexema) {
+                let message = format!("duplicate type parameter '{}'", name.lexema);
+                let diagnostic = Diagnostic::new(ErrorCode::DuplicateArgument, &message, name.row, name.col, name.lexema.chars().count());
+                return Err(CompilationError::SintaxError(Box::new(diagnostic)));
+            }
+        }
+
+        Ok(params.into_iter().map(|(param, _)| param).collect())
+    }
+
+    fn id_list(&self, node: usize) -> Vec<String> {
+        let mut names = Vec::new();
+        let mut c = self.tree.children(node);
+
+        loop {
+            names.push(self.tree.token(c[0]).lexema.clone());
+            c = self.tree.children(c[1]);
+
+            if c.is_empty() {
+                break;
+            }
+
+            c = &c[1..];
+        }
+
+        names
+    }
+
+    // async ASYNC_STATEMENT, com as posicoes tiradas do async
+    fn async_statement(&self, c: &[usize], decorator_list: Vec<Expr>) -> Result<Stmt, CompilationError> {
+        let tk = self.tree.token(c[0]);
+        let node = match self.tree.state(c[1]) {
+            PossibleStates::ASYNC_STATEMENT => self.tree.children(c[1])[0],
+            _ => c[1]
+        };
+
+        let mut stmt = match self.tree.state(node) {
+            PossibleStates::FUNCTION_DEF => self.definition(node, decorator_list, true)?,
+            PossibleStates::FOR_STATEMENT => {
+                self.check_async(tk, "async for")?;
+                self.for_statement(node, true)?
+            },
+            _ => {
+                self.check_async(tk, "async with")?;
+                self.with_statement(node, true)?
+            }
+        };
+
+        stmt.row = tk.row;
+        stmt.col = tk.col;
+
+        Ok(stmt)
+    }
+
+    fn for_statement(&self, node: usize, is_async: bool) -> Result<Stmt, CompilationError> {
+        let c = self.tree.children(node);
+        let tk = self.tree.token(c[0]);
+
+        Ok(Stmt {
+            kind: StmtKind::For {
+                target: self.target_list(c[1])?,
+                iter: check_value(self.star_expressions(c[3])?)?,
+                body: self.scope(c[5])?,
+                orelse: self.loop_else(c[6])?,
+                is_async
+            },
+            row: tk.row,
+            col: tk.col
+        })
+    }
+
+    // WITH_STATEMENT -> with WITH_ITEM WITH_ITEMS_D : SCOPE
+    fn with_statement(&self, node: usize, is_async: bool) -> Result<Stmt, CompilationError> {
+        let c = self.tree.children(node);
+        let tk = self.tree.token(c[0]);
+        let mut items = Vec::new();
+        let mut item = c[1];
+        let mut rest = c[2];
+
+        loop {
+            let i = self.tree.children(item);
+
+            items.push(WithItem {
+                context_expr: self.expression(i[0])?,
+                optional_vars: match self.tree.children(i[1]).get(1) {
+                    Some(&target) => {
+                        let target = self.star_or_expression(target)?;
+
+                        check_target(&target)?;
+                        Some(target)
+                    },
+                    None => None
+                }
+            });
+
+            match self.tree.children(rest) {
+                &[_, next_item, next_rest] => {
+                    item = next_item;
+                    rest = next_rest;
+                },
+                _ => break
+            }
+        }
+
+        Ok(Stmt {
+            kind: StmtKind::With {
+                items,
+                body: self.scope(c[4])?,
+                is_async
+            },
+            row: tk.row,
+            col: tk.col
+        })
+    }
+
+    // print >>destino, a, b, -- a virgula no final suprime a quebra de linha
+    fn print_statement(&self, node: usize) -> Result<StmtKind, CompilationError> {
+        let (dest, first, mut d) = match *self.tree.children(node) {
+            [_, dest, d] => (Some(self.expression(dest)?), None, d),
+            [value, d] => (None, Some(self.expression(value)?), d),
+            _ => return Ok(StmtKind::Print {
+                dest: None,
+                values: Vec::new(),
+                nl: true
+            })
+        };
+
+        let mut values: Vec<Expr> = first.into_iter().collect();
+        let mut nl = true;
+
+        while let &[comma, rest] = self.tree.children(d) {
+            match *self.tree.children(rest) {
+                [value, next] => {
+                    values.push(self.expression(value)?);
+                    d = next;
+                },
+                _ => {
+                    if values.is_empty() {
+                        return Err(error(ErrorCode::InvalidSyntax, self.tree.token(comma), "invalid syntax"));
+                    }
+
+                    nl = false;
+                    break;
+                }
+            }
+        }
+
+        Ok(StmtKind::Print {
+            dest,
+            values,
+            nl
+        })
+    }
+
+    // MATCH_STATEMENT -> match SUBJECT : eos INDENT CASE_BLOCKS DEDENT
+    fn match_statement(&self, node: usize) -> Result<Stmt, CompilationError> {
+        let c = self.tree.children(node);
+        let tk = self.tree.token(c[0]);
+        let subject = check_value(self.star_expressions(c[1])?)?;
+        let mut cases: Vec<MatchCase> = Vec::new();
+        let mut node = c[5];
+
+        while let &[case, rest] = self.tree.children(node) {
+            let b = self.tree.children(case);
+
+            if let Some(previous) = cases.last() {
+                if previous.guard.is_none() {
+                    check_reachable(&previous.pattern)?;
+                }
+            }
+
+            let pattern = self.patterns(b[1])?;
+            let mut names = Vec::new();
+            bound_names(&pattern, &mut names)?;
+
+            cases.push(MatchCase {
+                pattern,
+                guard: match self.tree.children(b[2]).get(1) {
+                    Some(&guard) => Some(self.expression(guard)?),
+                    None => None
+                },
+                body: self.scope(b[4])?
+            });
+
+            node = rest;
+        }
+
+        Ok(Stmt {
+            kind: StmtKind::Match {
+                subject,
+                cases
+            },
+            row: tk.row,
+            col: tk.col
+        })
+    }
+
+    fn pattern_at(&self, kind: PatternKind, tk: &Token, (end_row, end_col): (usize, usize)) -> Pattern {
+        Pattern {
+            kind,
+            row: tk.row,
+            col: tk.col,
+            end_row,
+            end_col
+        }
+    }
+
+    // PATTERNS sem parenteses: a, b vira uma sequencia
+    fn patterns(&self, node: usize) -> Result<Pattern, CompilationError> {
+        let (mut items, comma) = self.comma_list(node, &|n| self.maybe_star_pattern(n))?;
+
+        if comma {
+            let (row, col) = (items[0].row, items[0].col);
+            return self.sequence_pattern(items, (row, col), self.end_of(node));
+        }
+
+        let pattern = items.pop().unwrap();
+
+        if let PatternKind::Star(_) = pattern.kind {
+            return Err(pattern_error(ErrorCode::InvalidPattern, &pattern, "can't use starred pattern here"));
+        }
+
+        Ok(pattern)
+    }
+
+    fn sequence_pattern(
+        &self,
+        items: Vec<Pattern>,
+        (row, col): (usize, usize),
+        (end_row, end_col): (usize, usize)
+    ) -> Result<Pattern, CompilationError> {
+        let mut stars = items.iter().filter(|p| matches!(p.kind, PatternKind::Star(_)));
+
+        if let (Some(_), Some(second)) = (stars.next(), stars.next()) {
+            return Err(pattern_error(ErrorCode::InvalidPattern, second, "multiple starred names in sequence pattern"));
+        }
+
+        Ok(Pattern {
+            kind: PatternKind::Sequence(items),
+            row,
+            col,
+            end_row,
+            end_col
+        })
+    }
+
+    // MAYBE_STAR_PATTERN -> * id | PATTERN
+    fn maybe_star_pattern(&self, node: usize) -> Result<Pattern, CompilationError> {
+        let c = self.tree.children(node);
+
+        match *c {
+            [star, name] => Ok(self.pattern_at(PatternKind::Star(self.capture_name(name)), self.tree.token(star), self.end_of(name))),
+            _ => self.pattern(c[0])
+        }
+    }
+
+    // _ e o coringa e nao captura nada
+    fn capture_name(&self, node: usize) -> Option<String> {
+        match self.tree.token(node).lexema.as_str() {
+            "_" => None,
+            name => Some(name.to_string())
+        }
+    }
+
+    // PATTERN -> OR_PATTERN AS_PATTERN_E
+    fn pattern(&self, node: usize) -> Result<Pattern, CompilationError> {
+        let c = self.tree.children(node);
+        let (first, rest) = self.chain(c[0]);
+        let mut alternatives = vec![self.closed_pattern(first)?];
+
+        for (_, alternative) in rest {
+            check_reachable(alternatives.last().unwrap())?;
+            alternatives.push(self.closed_pattern(alternative)?);
+        }
+
+        let pattern = if alternatives.len() == 1 {
+            alternatives.pop().unwrap()
+        } else {
+            let mut expected = Vec::new();
+            bound_names(&alternatives[0], &mut expected)?;
+            expected.sort();
+
+            for alternative in &alternatives[1..] {
+                let mut names = Vec::new();
+                bound_names(alternative, &mut names)?;
+                names.sort();
+
+                if names != expected {
+                    return Err(pattern_error(ErrorCode::InvalidPattern, alternative, "alternative patterns bind different names"));
+                }
+            }
+
+            let (row, col) = (alternatives[0].row, alternatives[0].col);
+            let last = alternatives.last().unwrap();
+            let (end_row, end_col) = (last.end_row, last.end_col);
+
+            Pattern {
+                kind: PatternKind::Or(alternatives),
+                row,
+                col,
+                end_row,
+                end_col
+            }
+        };
+
+        match self.tree.children(c[1]).get(1) {
+            Some(&name) => {
+                let tk = self.tree.token(name);
+
+                if tk.lexema == "_" {
+                    return Err(error(ErrorCode::InvalidPattern, tk, "cannot use '_' as a target"));
+                }
+
+                let (row, col) = (pattern.row, pattern.col);
+                let (end_row, end_col) = token_end(tk);
+
+                Ok(Pattern {
+                    kind: PatternKind::As {
+                        pattern: Some(Box::new(pattern)),
+                        name: Some(tk.lexema.clone())
+                    },
+                    row,
+                    col,
+                    end_row,
+                    end_col
+                })
+            },
+            None => Ok(pattern)
+        }
+    }
+
+    fn closed_pattern(&self, node: usize) -> Result<Pattern, CompilationError> {
+        let c = self.tree.children(node);
+
+        if self.tree.state(c[0]) == PossibleStates::LITERAL_PATTERN {
+            return self.literal_pattern(c[0]);
+        }
+
+        let tk = self.tree.token(c[0]);
+
+        match tk.tk_type {
+            TkType::Operator("OPERATOR{PARENTESES_ESQUERDO}") | TkType::Operator("OPERATOR{COLCHETES_ESQUERDO}") => {
+                let (mut items, comma) = match self.tree.children(c[1]).first() {
+                    Some(&patterns) => self.comma_list(patterns, &|n| self.maybe_star_pattern(n))?,
+                    None => (Vec::new(), true)
+                };
+
+                // (p) so agrupa, (p,) e [p] sao sequencias
+                if !comma && tk.tk_type == TkType::Operator("OPERATOR{PARENTESES_ESQUERDO}") {
+                    let pattern = items.pop().unwrap();
+
+                    if let PatternKind::Star(_) = pattern.kind {
+                        return Err(pattern_error(ErrorCode::InvalidPattern, &pattern, "can't use starred pattern here"));
+                    }
+
+                    return Ok(pattern);
+                }
+
+                self.sequence_pattern(items, (tk.row, tk.col), self.end_of(c[2]))
+            },
+            TkType::Operator("OPERATOR{CHAVES_ESQUERDA}") => self.mapping_pattern(tk, c[1], self.end_of(c[2])),
+            _ => {
+                let mut value = self.name(c[0]);
+                let mut chain = self.tree.children(c[1]);
+
+                while let &[_, attr, rest] = chain {
+                    let (row, col) = (value.row, value.col);
+                    let (end_row, end_col) = token_end(self.tree.token(attr));
+
+                    value = Expr {
+                        kind: ExprKind::Attribute {
+                            value: Box::new(value),
+                            attr: self.tree.token(attr).lexema.clone()
+                        },
+                        row,
+                        col,
+                        end_row,
+                        end_col
+                    };
+                    chain = self.tree.children(rest);
+                }
+
+                if let Some(&arguments) = self.tree.children(c[2]).get(1) {
+                    return self.class_pattern(value, arguments, self.end_of(c[2]));
+                }
+
+                let end = end(&value);
+                let kind = match value.kind {
+                    ExprKind::Name(_) => PatternKind::As {
+                        pattern: None,
+                        name: self.capture_name(c[0])
+                    },
+                    _ => PatternKind::Value(value)
+                };
+
+                Ok(self.pattern_at(kind, tk, end))
+            }
+        }
+    }
+
+    // LITERAL_PATTERN -> - NUMBER COMPLEX_NUMBER_E | NUMBER COMPLEX_NUMBER_E | string | None | True | False
+    fn literal_pattern(&self, node: usize) -> Result<Pattern, CompilationError> {
+        let c = self.tree.children(node);
+
+        if self.tree.state(c[0]) == PossibleStates::NUMBER {
+            let number = self.complex_number(self.number(c[0]), c[1])?;
+            let (row, col) = (number.row, number.col);
+            let (end_row, end_col) = end(&number);
+
+            return Ok(Pattern {
+                kind: PatternKind::Value(number),
+                row,
+                col,
+                end_row,
+                end_col
+            });
+        }
+
+        let tk = self.tree.token(c[0]);
+
+        let kind = match tk.tk_type {
+            TkType::Operator("OPERATOR{MENOS}") => {
+                let number = self.expr_to(ExprKind::UnaryOp {
+                    op: UnaryOperator::USub,
+                    operand: Box::new(self.number(c[1]))
+                }, tk, self.end_of(c[1]));
+
+                PatternKind::Value(self.complex_number(number, c[2])?)
+            },
+            TkType::ReservedWord("RWORD{NONE}") => PatternKind::Singleton(Constant::None),
+            TkType::ReservedWord("RWORD{TRUE}") => PatternKind::Singleton(Constant::Bool(true)),
+            TkType::ReservedWord("RWORD{FALSE}") => PatternKind::Singleton(Constant::Bool(false)),
+            _ if is_f_string(tk) => return Err(error(ErrorCode::InvalidPattern, tk, "patterns may only match literals and attribute lookups")),
+            _ => PatternKind::Value(self.expr(ExprKind::Constant(Constant::Str(tk.lexema.clone())), tk))
+        };
+
+        let end = match &kind {
+            PatternKind::Value(value) => end(value),
+            _ => token_end(tk)
+        };
+
+        Ok(self.pattern_at(kind, tk, end))
+    }
+
+    // NUMBER -> int | float | imaginary
+    fn number(&self, node: usize) -> Expr {
+        let tk = self.tree.token(self.tree.children(node)[0]);
+
+        let constant = match tk.tk_type {
+            TkType::Literal(LiteralTypes::Int) => Constant::Int(tk.lexema.clone()),
+            TkType::Literal(LiteralTypes::Imaginary) => Constant::Imaginary(tk.lexema.clone()),
+            _ => Constant::Float(tk.lexema.clone())
+        };
+
+        self.expr(ExprKind::Constant(constant), tk)
+    }
+
+    // COMPLEX_NUMBER_E -> + NUMBER | - NUMBER | e, com a parte real antes e a imaginaria depois do sinal
+    fn complex_number(&self, real: Expr, node: usize) -> Result<Expr, CompilationError> {
+        let c = self.tree.children(node);
+
+        if c.is_empty() {
+            return Ok(real);
+        }
+
+        let is_imaginary = |e: &Expr| matches!(e.kind, ExprKind::Constant(Constant::Imaginary(_)));
+
+        let unsigned = match &real.kind {
+            ExprKind::UnaryOp { operand, .. } => operand,
+            _ => &real
+        };
+
+        if is_imaginary(unsigned) {
+            return Err(expr_error(ErrorCode::InvalidPattern, unsigned, "real number required in complex literal"));
+        }
+
+        let imaginary = self.number(c[1]);
+
+        if !is_imaginary(&imaginary) {
+            return Err(expr_error(ErrorCode::InvalidPattern, &imaginary, "imaginary number required in complex literal"));
+        }
+
+        let op = match self.tree.token(c[0]).tk_type {
+            TkType::Operator("OPERATOR{MAIS}") => Operator::Add,
+            _ => Operator::Sub
+        };
+
+        let (row, col) = (real.row, real.col);
+        let (end_row, end_col) = end(&imaginary);
+
+        Ok(Expr {
+            kind: ExprKind::BinOp {
+                left: Box::new(real),
+                op,
+                right: Box::new(imaginary)
+            },
+            row,
+            col,
+            end_row,
+            end_col
+        })
+    }
+
+    // MAPPING_ITEM -> CLOSED_PATTERN : PATTERN | ** id
+    fn mapping_pattern(&self, tk: &Token, node: usize, end: (usize, usize)) -> Result<Pattern, CompilationError> {
+        let mut keys = Vec::new();
+        let mut patterns = Vec::new();
+        let mut rest: Option<&Token> = None;
+
+        let (items, _) = self.comma_list(node, &|n| Ok(n))?;
+
+        for item in items {
+            let i = self.tree.children(item);
+
+            if let Some(rest) = rest {
+                return Err(error(ErrorCode::InvalidPattern, rest, "double star pattern must be last in mapping pattern"));
+            }
+
+            if i.len() == 2 {
+                let name = self.tree.token(i[1]);
+
+                if name.lexema == "_" {
+                    return Err(error(ErrorCode::InvalidPattern, name, "cannot use '_' as a target"));
+                }
+
+                rest = Some(name);
+                continue;
+            }
+
+            let key = self.closed_pattern(i[0])?;
+
+            let key = match key.kind {
+                PatternKind::Value(value) => value,
+                PatternKind::Singleton(constant) => Expr {
+                    kind: ExprKind::Constant(constant),
+                    row: key.row,
+                    col: key.col,
+                    end_row: key.end_row,
+                    end_col: key.end_col
+                },
+                _ => return Err(span_error(ErrorCode::InvalidPattern, (key.row, key.col), (key.end_row, key.end_col), "mapping pattern keys may only match literals and attribute lookups"))
+            };
+
+            // a.b so e avaliado na hora do match, literais repetidos ja sao erro aqui
+            if let Some(literal) = literal_repr(&key) {
+                if keys.iter().any(|k| literal_repr(k).as_ref() == Some(&literal)) {
+                    return Err(expr_error(ErrorCode::InvalidPattern, &key, &format!("mapping pattern checks duplicate key ({})", literal)));
+                }
+            }
+
+            keys.push(key);
+            patterns.push(self.pattern(i[2])?);
+        }
+
+        Ok(self.pattern_at(PatternKind::Mapping {
+            keys,
+            patterns,
+            rest: rest.map(|name| name.lexema.clone())
+        }, tk, end))
+    }
+
+    // CLASS_PATTERN_ARGUMENT -> PATTERN KEYWORD_PATTERN_E
+    fn class_pattern(&self, cls: Expr, node: usize, end: (usize, usize)) -> Result<Pattern, CompilationError> {
+        let mut patterns = Vec::new();
+        let mut kwd_attrs: Vec<String> = Vec::new();
+        let mut kwd_patterns = Vec::new();
+
+        let (arguments, _) = self.comma_list(node, &|n| Ok(n))?;
+
+        for argument in arguments {
+            let a = self.tree.children(argument);
+            let pattern = self.pattern(a[0])?;
+
+            match self.tree.children(a[1]).get(1) {
+                Some(&value) => {
+                    let name = match &pattern.kind {
+                        PatternKind::As { pattern: None, name: Some(name) } => name.clone(),
+                        _ => return Err(pattern_error(ErrorCode::InvalidPattern, &pattern, "keyword pattern must be a name"))
+                    };
+
+                    if kwd_attrs.contains(&name) {
+                        return Err(pattern_error(ErrorCode::InvalidPattern, &pattern, &format!("attribute name repeated in class pattern: {}", name)));
+                    }
+
+                    kwd_attrs.push(name);
+                    kwd_patterns.push(self.pattern(value)?);
+                },
+                None => {
+                    if !kwd_attrs.is_empty() {
+                        return Err(pattern_error(ErrorCode::InvalidPattern, &pattern, "positional patterns follow keyword patterns"));
+                    }
+
+                    patterns.push(pattern);
+                }
+            }
+        }
+
+        let (row, col) = (cls.row, cls.col);
+        let (end_row, end_col) = end;
+
+        Ok(Pattern {
+            kind: PatternKind::Class {
+                cls,
+                patterns,
+                kwd_attrs,
+                kwd_patterns
+            },
+            row,
+            col,
+            end_row,
+            end_col
+        })
+    }
+
+    fn definition(&self, node: usize, decorator_list: Vec<Expr>, is_async: bool) -> Result<Stmt, CompilationError> {
+        let c = self.tree.children(node);
+        let tk = self.tree.token(c[0]);
+        let name = self.tree.token(c[1]).lexema.clone();
+
+        let kind = match self.tree.state(node) {
+            PossibleStates::FUNCTION_DEF => {
+                let args = self.parameters(c[3])?;
+                let returns = self.optional_expression(&c[5..6], PossibleStates::RETURNS_E)?.map(|r| *r);
+                let (body, frame) = self.in_frame(Frame::function(is_async), &|| self.scope(c[7]))?;
+
+                let is_generator = match frame {
+                    Frame::Function { is_generator: true, return_value: Some((row, col)), .. } if is_async => {
+                        return Err(error_at(ErrorCode::InvalidContext, row, col, "'return' with value in async generator"));
+                    },
+                    Frame::Function { is_generator, .. } => is_generator,
+                    Frame::Class => false
+                };
+
+                StmtKind::FunctionDef {
+                    name,
+                    args,
+                    body,
+                    decorator_list,
+                    returns,
+                    is_async,
+                    is_generator
+                }
+            },
+            _ => {
+                let (bases, keywords) = match self.tree.children(c[2]).get(1) {
+                    Some(&arguments) => self.call_arguments(arguments)?,
+                    None => (Vec::new(), Vec::new())
+                };
+
+                StmtKind::ClassDef {
+                    name,
+                    bases,
+                    keywords,
+                    body: self.in_frame(Frame::Class, &|| self.scope(c[4]))?.0,
+                    decorator_list
+                }
+            }
+        };
+
+        Ok(Stmt {
+            kind,
+            row: tk.row,
+            col: tk.col
+        })
+    }
+
+    fn else_statement(&self, node: usize) -> Result<Vec<Stmt>, CompilationError> {
+        let chain_depth = self.chain_depth.get();
+        let result = self.elif_chain(node);
+        self.chain_depth.set(chain_depth);
+
+        result
+    }
+
+    // cada elif vira um if no else do anterior, entao a cadeia e lida num laco mas a AST continua
+    // aninhada e quem a percorre recursa uma vez por elif: a cadeia conta em MAX_DEPTH
+    fn elif_chain(&self, node: usize) -> Result<Vec<Stmt>, CompilationError> {
+        let mut clauses = Vec::new();
+        let mut node = node;
+
+        let orelse = loop {
+            let c = self.tree.children(node);
+
+            if c.is_empty() {
+                break Vec::new();
+            }
+
+            if let Some(TkType::ReservedWord("RWORD{ELSE}")) = self.terminal(c[0]) {
+                break self.scope(c[2])?;
+            }
+
+            let tk = self.tree.token(c[0]);
+            let depth = self.chain_depth.get() + 1;
+
+            if self.depth.get() + depth >= MAX_DEPTH {
+                return Err(too_deeply_nested(tk));
+            }
+
+            self.chain_depth.set(depth);
+            clauses.push((tk, self.expression(c[1])?, self.scope(c[3])?));
+            node = c[4];
+        };
+
+        let chain = clauses.into_iter().rev().fold(orelse, |orelse, (tk, test, body)| {
+            vec![Stmt {
+                kind: StmtKind::If { test, body, orelse },
+                row: tk.row,
+                col: tk.col
+            }]
+        });
+
+        Ok(chain)
+    }
+
+    // LOOP_ELSE_E -> else : SCOPE | e
+    fn loop_else(&self, node: usize) -> Result<Vec<Stmt>, CompilationError> {
+        match self.tree.children(node).get(2) {
+            Some(&scope) => self.scope(scope),
+            None => Ok(Vec::new())
+        }
+    }
+
+    fn expression_statement(&self, node: usize) -> Result<Stmt, CompilationError> {
+        let c = self.tree.children(node);
+
+        if c.len() == 1 {
+            let value = self.expression(c[0])?;
+            let (row, col) = (value.row, value.col);
+
+            return Ok(Stmt {
+                kind: StmtKind::Expr(value),
+                row,
+                col
+            });
+        }
+
+        let first = self.star_expressions(c[0])?;
+        let (row, col) = (first.row, first.col);
+
+        let kind = match self.tree.children(c[1]).first() {
+            None => StmtKind::Expr(check_value(first)?),
+            Some(&assignment) => {
+                let a = self.tree.children(assignment);
+                let op = self.tree.token(a[0]);
+
+                if op.tk_type == TkType::Operator("OPERATOR{IGUAL}") {
+                    let mut targets = vec![first];
+                    let mut chain = a[1];
+
+                    // ASSIGNMENT_CHAIN -> STAR_EXPRESSIONS ASSIGNMENT_CHAINL | YIELD_EXPRESSION
+                    let value = loop {
+                        let l = self.tree.children(chain);
+
+                        if l.len() == 1 {
+                            break self.expression(l[0])?;
+                        }
+
+                        let value = self.star_expressions(l[0])?;
+
+                        match self.tree.children(l[1]).get(1) {
+                            Some(&next) => {
+                                targets.push(value);
+                                chain = next;
+                            },
+                            None => break check_value(value)?
+                        }
+                    };
+
+                    for target in &targets {
+                        check_target(target)?;
+                    }
+
+                    StmtKind::Assign {
+                        targets,
+                        value
+                    }
+                } else if op.tk_type == TkType::Operator("OPERATOR{DOIS_PONTOS}") {
+                    match first.kind {
+                        ExprKind::Name(_) | ExprKind::Attribute { .. } | ExprKind::Subscript { .. } => {},
+                        ExprKind::Tuple(_) => return Err(expr_error(ErrorCode::InvalidTarget, &first, "only single target (not tuple) can be annotated")),
+                        ExprKind::List(_) => return Err(expr_error(ErrorCode::InvalidTarget, &first, "only single target (not list) can be annotated")),
+                        _ => return Err(expr_error(ErrorCode::InvalidTarget, &first, "illegal target for annotation"))
+                    }
+
+                    // ANNOTATION_VALUE_E -> = ANNOTATED_VALUE | e
+                    let value = match self.tree.children(a[2]).get(1) {
+                        Some(&value) => {
+                            let v = self.tree.children(value)[0];
+
+                            Some(match self.tree.state(v) {
+                                PossibleStates::STAR_EXPRESSIONS => check_value(self.star_expressions(v)?)?,
+                                _ => self.expression(v)?
+                            })
+                        },
+                        None => None
+                    };
+
+                    StmtKind::AnnAssign {
+                        simple: matches!(first.kind, ExprKind::Name(_)),
+                        target: first,
+                        annotation: self.expression(a[1])?,
+                        value
+                    }
+                } else {
+                    match first.kind {
+                        ExprKind::Name(_) | ExprKind::Attribute { .. } | ExprKind::Subscript { .. } => {},
+                        _ => return Err(expr_error(ErrorCode::InvalidTarget, &first, &format!("'{}' is an illegal expression for augmented assignment", describe(&first))))
+                    }
+
+                    StmtKind::AugAssign {
+                        target: first,
+                        op: operator(op),
+                        value: self.expression(a[1])?
+                    }
+                }
+            }
+        };
+
+        Ok(Stmt {
+            kind,
+            row,
+            col
+        })
+    }
+
+    fn parameters(&self, node: usize) -> Result<Arguments, CompilationError> {
+        let mut arguments = Arguments::default();
+        let mut names = HashSet::new();
+        let mut star: Option<&Token> = None;
+        let mut slash = false;
+        let mut default = false;
+        let mut node = node;
+
+        loop {
+            let c = self.tree.children(node);
+
+            if c.is_empty() {
+                break;
+            }
+
+            let p = self.tree.children(c[0]);
+            let tk = self.tree.token(p[0]);
+
+            if arguments.kwarg.is_some() {
+                return Err(error(ErrorCode::InvalidArguments, tk, "arguments cannot follow var-keyword argument"));
+            }
+
+            // o nome e seguido pela anotacao e pelo valor padrao, quando existirem
+            let named = match tk.tk_type {
+                TkType::Identifier => Some(p),
+                TkType::Operator("OPERATOR{VEZES}") => Some(self.tree.children(p[1])).filter(|s| !s.is_empty()),
+                TkType::Operator("OPERATOR{NOME_PARAMETRO}") => Some(&p[1..]),
+                _ => None
+            };
+
+            let arg = match named {
+                Some(named) => {
+                    let name = self.tree.token(named[0]);
+
+                    Some(Arg {
+                        name: name.lexema.clone(),
+                        annotation: self.optional_expression(&named[1..], PossibleStates::ANNOTATION_E)?,
+                        default: self.optional_expression(&named[1..], PossibleStates::DEFAULT_E)?,
+                        row: name.row,
+                        col: name.col
+                    })
+                },
+                None => None
+            };
+
+            if let Some(arg) = &arg {
+                if !names.insert(arg.name.clone()) {
+                    let message = format!("duplicate argument '{}' in function definition", arg.name);
+                    let diagnostic = Diagnostic::new(ErrorCode::DuplicateArgument, &message, arg.row, arg.col, arg.name.chars().count());
+                    return Err(CompilationError::SintaxError(Box::new(diagnostic)));
+                }
+            }
+
+            match tk.tk_type {
+                TkType::Identifier => {
+                    let arg = arg.unwrap();
+
+                    if star.is_some() {
+                        arguments.kwonlyargs.push(arg);
+                    } else {
+                        if arg.default.is_some() {
+                            default = true;
+                        } else if default {
+                            return Err(error(ErrorCode::InvalidArguments, tk, "non-default argument follows default argument"));
+                        }
+
+                        arguments.args.push(arg);
+                    }
+                },
+                TkType::Operator("OPERATOR{BARRA}") => {
+                    if slash {
+                        return Err(error(ErrorCode::InvalidArguments, tk, "/ may appear only once"));
+                    }
+
+                    if star.is_some() {
+                        return Err(error(ErrorCode::InvalidArguments, tk, "/ must be ahead of *"));
+                    }
+
+                    if arguments.args.is_empty() {
+                        return Err(error(ErrorCode::InvalidArguments, tk, "at least one argument must precede /"));
+                    }
+
+                    slash = true;
+                    arguments.posonlyargs.append(&mut arguments.args);
+                },
+                TkType::Operator("OPERATOR{VEZES}") => {
+                    if star.is_some() {
+                        return Err(error(ErrorCode::InvalidArguments, tk, "* argument may appear only once"));
+                    }
+
+                    star = Some(tk);
+                    arguments.vararg = arg;
+                },
+                _ => arguments.kwarg = arg
+            }
+
+            continue_list(&mut node, c[1], self.tree);
+        }
+
+        if let Some(tk) = star {
+            if arguments.vararg.is_none() && arguments.kwonlyargs.is_empty() {
+                return Err(error(ErrorCode::InvalidArguments, tk, "named arguments must follow bare *"));
+            }
+        }
+
+        Ok(arguments)
+    }
+
+    // X -> op EXPRESSION | e, procurado entre os irmaos pelo estado
+    fn optional_expression(&self, nodes: &[usize], state: PossibleStates) -> Result<Option<Box<Expr>>, CompilationError> {
+        match nodes.iter().find(|&&n| self.tree.state(n) == state) {
+            Some(&n) => match self.tree.children(n).get(1) {
+                Some(&value) => Ok(Some(Box::new(self.expression(value)?))),
+                None => Ok(None)
+            },
+            None => Ok(None)
+        }
+    }
+
+    // x := valor, o alvo so pode ser um nome
+    fn named_expression(&self, target: Expr, value: usize) -> Result<Expr, CompilationError> {
+        if let ExprKind::Name(_) = target.kind {
+            let (row, col) = (target.row, target.col);
+            let value = self.expression(value)?;
+            let (end_row, end_col) = end(&value);
+
+            return Ok(Expr {
+                kind: ExprKind::NamedExpr {
+                    target: Box::new(target),
+                    value: Box::new(value)
+                },
+                row,
+                col,
+                end_row,
+                end_col
+            });
+        }
+
+        Err(expr_error(ErrorCode::InvalidTarget, &target, &format!("cannot use assignment expressions with {}", describe(&target))))
+    }
+
+    fn arguments(&self, func: Expr, node: usize, (end_row, end_col): (usize, usize)) -> Result<Expr, CompilationError> {
+        let (args, keywords) = self.call_arguments(node)?;
+        let (row, col) = (func.row, func.col);
+
+        Ok(Expr {
+            kind: ExprKind::Call {
+                func: Box::new(func),
+                args,
+                keywords
+            },
+            row,
+            col,
+            end_row,
+            end_col
+        })
+    }
+
+    fn call_arguments(&self, node: usize) -> Result<(Vec<Expr>, Vec<Keyword>), CompilationError> {
+        let mut args = Vec::new();
+        let mut keywords: Vec<Keyword> = Vec::new();
+        let mut generator: Option<usize> = None;
+        let mut comma = false;
+        let mut node = node;
+
+        loop {
+            let c = self.tree.children(node);
+
+            if c.is_empty() {
+                break;
+            }
+
+            let a = self.tree.children(c[0]);
+            let unpacking = keywords.iter().any(|k| k.arg.is_none());
+
+            match self.terminal(a[0]) {
+                Some(TkType::Operator("OPERATOR{VEZES}")) => {
+                    let tk = self.tree.token(a[0]);
+
+                    if unpacking {
+                        return Err(error(ErrorCode::InvalidArguments, tk, "iterable argument unpacking follows keyword argument unpacking"));
+                    }
+
+                    let value = self.expression(a[1])?;
+                    let end = end(&value);
+                    args.push(self.expr_to(ExprKind::Starred(Box::new(value)), tk, end));
+                },
+                Some(TkType::Operator("OPERATOR{NOME_PARAMETRO}")) => keywords.push(Keyword {
+                    arg: None,
+                    value: self.expression(a[1])?
+                }),
+                _ => {
+                    let value = self.expression(a[0])?;
+                    let l = self.tree.children(a[1]);
+
+                    if l.is_empty() {
+                        if unpacking {
+                            return Err(expr_error(ErrorCode::InvalidArguments, &value, "positional argument follows keyword argument unpacking"));
+                        }
+
+                        if !keywords.is_empty() {
+                            return Err(expr_error(ErrorCode::InvalidArguments, &value, "positional argument follows keyword argument"));
+                        }
+
+                        args.push(value);
+                    } else if self.tree.state(l[0]) == PossibleStates::COMP_FOR {
+                        generator = Some(args.len());
+                        let (row, col) = (value.row, value.col);
+                        let (end_row, end_col) = self.end_of(l[0]);
+
+                        let generator = Expr {
+                            kind: ExprKind::GeneratorExp {
+                                elt: Box::new(value),
+                                generators: self.comprehension(l[0])?
+                            },
+                            row,
+                            col,
+                            end_row,
+                            end_col
+                        };
+
+                        self.check_comprehension(&generator)?;
+                        args.push(generator);
+                    } else {
+                        let name = match &value.kind {
+                            ExprKind::Name(name) => name.clone(),
+                            ExprKind::Constant(Constant::Bool(_)) | ExprKind::Constant(Constant::None) => {
+                                return Err(expr_error(ErrorCode::InvalidTarget, &value, &format!("cannot assign to {}", describe(&value))));
+                            },
+                            _ => return Err(error(ErrorCode::InvalidArguments, self.tree.token(l[0]), "expression cannot contain assignment, perhaps you meant \"==\"?"))
+                        };
+
+                        if keywords.iter().any(|k| k.arg.as_ref() == Some(&name)) {
+                            let message = format!("keyword argument repeated: {}", name);
+                            return Err(span_error(ErrorCode::InvalidArguments, (value.row, value.col), self.end_of(c[0]), &message));
+                        }
+
+                        keywords.push(Keyword {
+                            arg: Some(name),
+                            value: self.expression(l[1])?
+                        });
+                    }
+                }
+            }
+
+            comma = comma || !self.tree.children(c[1]).is_empty();
+            continue_list(&mut node, c[1], self.tree);
+        }
+
+        if let Some(i) = generator {
+            if args.len() + keywords.len() > 1 || comma {
+                return Err(expr_error(ErrorCode::InvalidArguments, &args[i], "Generator expression must be parenthesized"));
+            }
+        }
+
+        Ok((args, keywords))
+    }
+
+    fn expression_list(&self, node: usize) -> Result<Expr, CompilationError> {
+        let c = self.tree.children(node);
+        let first = self.expression(c[0])?;
+        let mut elts = Vec::new();
+        let mut tail = c[1];
+
+        while let Some((_, value)) = self.tail(tail) {
+            elts.push(self.expression(value)?);
+            tail = self.tree.children(tail)[2];
+        }
+
+        if elts.is_empty() {
+            return Ok(first);
+        }
+
+        let (row, col) = (first.row, first.col);
+        let (end_row, end_col) = end(elts.last().unwrap());
+        elts.insert(0, first);
+
+        Ok(Expr {
+            kind: ExprKind::Tuple(elts),
+            row,
+            col,
+            end_row,
+            end_col
+        })
+    }
+
+    // estados que so repassam o filho, como SUM -> TERM quando nao ha + nem -. Pular sem recursao
+    // evita uma chamada por nivel de precedencia em cada parentese
+    fn skip_chain(&self, node: usize) -> usize {
+        let mut node = node;
+
+        loop {
+            let c = self.tree.children(node);
+
+            let single = match self.tree.state(node) {
+                PossibleStates::INVERSION | PossibleStates::FACTOR => c.len() == 1,
+                PossibleStates::ASSIGNMENT_VALUE => self.tree.state(c[0]) != PossibleStates::EXPRESSION_LIST,
+                PossibleStates::EXPRESSION |
+                PossibleStates::NAMED_EXPRESSION |
+                PossibleStates::DISJUNCTION |
+                PossibleStates::CONJUNCTION |
+                PossibleStates::COMPARISON |
+                PossibleStates::BITWISE_OR |
+                PossibleStates::BITWISE_XOR |
+                PossibleStates::BITWISE_AND |
+                PossibleStates::SHIFT_EXPRESSION |
+                PossibleStates::SUM |
+                PossibleStates::TERM |
+                PossibleStates::POWER |
+                PossibleStates::PRIMARY => c.get(1).is_none_or(|&tail| self.tree.children(tail).is_empty()),
+                _ => false
+            };
+
+            if !single {
+                return node;
+            }
+
+            node = c[0];
+        }
+    }
+
+    fn expression(&self, node: usize) -> Result<Expr, CompilationError> {
+        let node = self.skip_chain(node);
+        let depth = self.depth.get();
+
+        // a conversao e recursiva, sem limite um arquivo valido derrubaria o processo
+        if depth >= MAX_NESTING || depth + self.chain_depth.get() >= MAX_DEPTH {
+            return Err(too_deeply_nested(self.tree.first_token(node).unwrap()));
+        }
+
+        self.depth.set(depth + 1);
+        let result = self.nested_expression(node);
+        self.depth.set(depth);
+
+        result
+    }
+
+    // cada caso num metodo proprio deixa o frame desta funcao pequeno
+    fn nested_expression(&self, node: usize) -> Result<Expr, CompilationError> {
+        let c = self.tree.children(node);
+
+        match self.tree.state(node) {
+            PossibleStates::EXPRESSION => self.conditional_expression(c),
+            PossibleStates::NAMED_EXPRESSION => {
+                let target = self.expression(c[0])?;
+
+                match self.tree.children(c[1]).get(1) {
+                    Some(&value) => self.named_expression(target, value),
+                    None => Ok(target)
+                }
+            },
+            PossibleStates::LAMBDA_EXPRESSION => self.lambda(c),
+            PossibleStates::DISJUNCTION | PossibleStates::CONJUNCTION => self.bool_operation(node),
+            PossibleStates::INVERSION | PossibleStates::FACTOR => self.unary_operation(c),
+            PossibleStates::COMPARISON => self.comparison(node),
+            PossibleStates::BITWISE_OR |
+            PossibleStates::BITWISE_XOR |
+            PossibleStates::BITWISE_AND |
+            PossibleStates::SHIFT_EXPRESSION |
+            PossibleStates::SUM |
+            PossibleStates::TERM => self.binary_operation(node),
+            PossibleStates::POWER => self.power(c),
+            PossibleStates::PRIMARY => {
+                let atom = self.expression(c[0])?;
+                self.trailers(atom, c[1])
+            },
+            PossibleStates::AWAIT_PRIMARY => {
+                let tk = self.tree.token(c[0]);
+                self.check_async(tk, "await")?;
+
+                let atom = self.expression(c[1])?;
+                let value = self.trailers(atom, c[2])?;
+                let end = end(&value);
+
+                Ok(self.expr_to(ExprKind::Await(Box::new(value)), tk, end))
+            },
+            PossibleStates::ASSIGNMENT_VALUE => self.expression_list(c[0]),
+            PossibleStates::YIELD_EXPRESSION => self.yield_expression(c),
+            PossibleStates::ATOM => self.atom(c),
+            state => panic!("Unexpected expression {:?}", state)
+        }
+    }
+
+    // EXPRESSION -> DISJUNCTION CONDITIONAL_E, com o if ... else ja presente
+    fn conditional_expression(&self, c: &[usize]) -> Result<Expr, CompilationError> {
+        let body = self.expression(c[0])?;
+        let conditional = self.tree.children(c[1]);
+        let (row, col) = (body.row, body.col);
+        let test = self.expression(conditional[1])?;
+        let orelse = self.expression(conditional[3])?;
+        let (end_row, end_col) = end(&orelse);
+
+        Ok(Expr {
+            kind: ExprKind::IfExp {
+                test: Box::new(test),
+                body: Box::new(body),
+                orelse: Box::new(orelse)
+            },
+            row,
+            col,
+            end_row,
+            end_col
+        })
+    }
+
+    fn lambda(&self, c: &[usize]) -> Result<Expr, CompilationError> {
+        let args = self.parameters(c[1])?;
+        let (body, _) = self.in_frame(Frame::function(false), &|| self.expression(c[3]))?;
+        let end = end(&body);
+
+        Ok(self.expr_to(ExprKind::Lambda {
+            args,
+            body: Box::new(body)
+        }, self.tree.token(c[0]), end))
+    }
+
+    fn bool_operation(&self, node: usize) -> Result<Expr, CompilationError> {
+        let (first, rest) = self.chain(node);
+        let left = self.expression(first)?;
+
+        let op = match self.tree.state(node) {
+            PossibleStates::CONJUNCTION => BoolOperator::And,
+            _ => BoolOperator::Or
+        };
+        let (row, col) = (left.row, left.col);
+        let mut values = vec![left];
+
+        for (_, operand) in rest {
+            values.push(self.expression(operand)?);
+        }
+
+        let (end_row, end_col) = end(values.last().unwrap());
+
+        Ok(Expr {
+            kind: ExprKind::BoolOp {
+                op,
+                values
+            },
+            row,
+            col,
+            end_row,
+            end_col
+        })
+    }
+
+    // INVERSION -> not INVERSION; FACTOR -> + FACTOR | - FACTOR | ~ FACTOR
+    fn unary_operation(&self, c: &[usize]) -> Result<Expr, CompilationError> {
+        let tk = self.tree.token(c[0]);
+        let operand = self.expression(c[1])?;
+        let end = end(&operand);
+
+        Ok(self.expr_to(ExprKind::UnaryOp {
+            op: unary_operator(tk),
+            operand: Box::new(operand)
+        }, tk, end))
+    }
+
+    // a < b < c vira um unico Compare com dois operadores
+    fn comparison(&self, node: usize) -> Result<Expr, CompilationError> {
+        let (first, rest) = self.chain(node);
+        let left = self.expression(first)?;
+
+        let mut ops = Vec::new();
+        let mut comparators = Vec::new();
+
+        for (operator, operand) in rest {
+            // is not: o segundo token fica em IS_NOT_E
+            let o = self.tree.children(operator);
+            let op = match (cmp_operator(self.tree.token(o[0])), o.get(1)) {
+                (CmpOperator::Is, Some(&not)) if !self.tree.children(not).is_empty() => CmpOperator::IsNot,
+                (op, _) => op
+            };
+
+            ops.push(op);
+            comparators.push(self.expression(operand)?);
+        }
+
+        let (row, col) = (left.row, left.col);
+        let (end_row, end_col) = end(comparators.last().unwrap());
+
+        Ok(Expr {
+            kind: ExprKind::Compare {
+                left: Box::new(left),
+                ops,
+                comparators
+            },
+            row,
+            col,
+            end_row,
+            end_col
+        })
+    }
+
+    fn binary_operation(&self, node: usize) -> Result<Expr, CompilationError> {
+        let (first, rest) = self.chain(node);
+        let chain_depth = self.chain_depth.get();
+
+        // cada operador e mais um nivel de BinOp acima dos operandos
+        self.chain_depth.set(chain_depth + rest.len());
+        let result = self.binary_chain(first, &rest);
+        self.chain_depth.set(chain_depth);
+
+        result
+    }
+
+    fn binary_chain(&self, first: usize, rest: &[(usize, usize)]) -> Result<Expr, CompilationError> {
+        let mut left = self.expression(first)?;
+
+        for &(op, operand) in rest {
+            let (row, col) = (left.row, left.col);
+            let right = self.expression(operand)?;
+            let (end_row, end_col) = end(&right);
+
+            left = Expr {
+                kind: ExprKind::BinOp {
+                    left: Box::new(left),
+                    op: operator(self.tree.token(op)),
+                    right: Box::new(right)
+                },
+                row,
+                col,
+                end_row,
+                end_col
+            };
+        }
+
+        Ok(left)
+    }
+
+    // POWERL -> ** FACTOR ja e associativo a direita
+    fn power(&self, c: &[usize]) -> Result<Expr, CompilationError> {
+        let left = self.expression(c[0])?;
+        let (tk, right) = self.tail(c[1]).unwrap();
+        let (row, col) = (left.row, left.col);
+        let right = self.expression(right)?;
+        let (end_row, end_col) = end(&right);
+
+        Ok(Expr {
+            kind: ExprKind::BinOp {
+                left: Box::new(left),
+                op: operator(tk),
+                right: Box::new(right)
+            },
+            row,
+            col,
+            end_row,
+            end_col
+        })
+    }
+
+    fn atom(&self, c: &[usize]) -> Result<Expr, CompilationError> {
+        let tk = self.tree.token(c[0]);
+
+        let kind = match tk.tk_type {
+            TkType::Operator("OPERATOR{PARENTESES_ESQUERDO}") => return self.display(tk, c[1], self.end_of(c[2])),
+            TkType::Operator("OPERATOR{COLCHETES_ESQUERDO}") => return self.display(tk, c[1], self.end_of(c[2])),
+            TkType::Operator("OPERATOR{CHAVES_ESQUERDA}") => return self.dict_or_set(tk, c[1], self.end_of(c[2])),
+            TkType::Identifier => ExprKind::Name(tk.lexema.clone()),
+            TkType::Literal(LiteralTypes::Int) => ExprKind::Constant(Constant::Int(tk.lexema.clone())),
+            TkType::Literal(LiteralTypes::Float) => ExprKind::Constant(Constant::Float(tk.lexema.clone())),
+            TkType::Literal(LiteralTypes::Imaginary) => ExprKind::Constant(Constant::Imaginary(tk.lexema.clone())),
+            TkType::Literal(LiteralTypes::String) if is_f_string(tk) => ExprKind::JoinedStr(tk.lexema.clone()),
+            TkType::Literal(LiteralTypes::String) => ExprKind::Constant(Constant::Str(tk.lexema.clone())),
+            TkType::ReservedWord("RWORD{TRUE}") => ExprKind::Constant(Constant::Bool(true)),
+            TkType::ReservedWord("RWORD{FALSE}") => ExprKind::Constant(Constant::Bool(false)),
+            TkType::ReservedWord("RWORD{NONE}") => ExprKind::Constant(Constant::None),
+            TkType::Operator("OPERATOR{RETICENCIAS}") => ExprKind::Constant(Constant::Ellipsis),
+            tk_type => panic!("Unexpected atom {:?}", tk_type)
+        };
+
+        Ok(self.expr(kind, tk))
+    }
+
+    fn trailers(&self, value: Expr, node: usize) -> Result<Expr, CompilationError> {
+        let chain_depth = self.chain_depth.get();
+        let result = self.trailer_chain(value, node);
+        self.chain_depth.set(chain_depth);
+
+        result
+    }
+
+    fn trailer_chain(&self, value: Expr, node: usize) -> Result<Expr, CompilationError> {
+        let mut value = value;
+        let mut node = node;
+
+        loop {
+            let c = self.tree.children(node);
+
+            if c.is_empty() {
+                return Ok(value);
+            }
+
+            // a.b.c: cada trailer e mais um nivel acima do anterior
+            let depth = self.chain_depth.get() + 1;
+
+            if self.depth.get() + depth >= MAX_DEPTH {
+                return Err(too_deeply_nested(self.tree.first_token(c[0]).unwrap()));
+            }
+
+            self.chain_depth.set(depth);
+
+            let t = self.tree.children(c[0]);
+            let (row, col) = (value.row, value.col);
+            let (end_row, end_col) = self.end_of(c[0]);
+
+            let kind = match self.tree.token(t[0]).tk_type {
+                TkType::Operator("OPERATOR{PARENTESES_ESQUERDO}") => {
+                    value = self.arguments(value, t[1], (end_row, end_col))?;
+                    node = c[1];
+                    continue;
+                },
+                TkType::Operator("OPERATOR{COLCHETES_ESQUERDO}") => ExprKind::Subscript {
+                    value: Box::new(value),
+                    slice: Box::new(self.subscript_list(t[1])?)
+                },
+                _ => ExprKind::Attribute {
+                    value: Box::new(value),
+                    attr: self.tree.token(t[1]).lexema.clone()
+                }
+            };
+
+            value = Expr {
+                kind,
+                row,
+                col,
+                end_row,
+                end_col
+            };
+            node = c[1];
+        }
+    }
+
+    // LIST -> ITEM LIST_D; LIST_D -> , LIST_E | e; LIST_E -> ITEM LIST_D | e
+    fn comma_list<T>(
+        &self,
+        node: usize,
+        item: &dyn Fn(usize) -> Result<T, CompilationError>
+    ) -> Result<(Vec<T>, bool), CompilationError> {
+        let mut items = Vec::new();
+        let mut comma = false;
+        let mut node = node;
+
+        loop {
+            let c = self.tree.children(node);
+
+            if c.is_empty() {
+                break;
+            }
+
+            items.push(item(c[0])?);
+
+            let d = self.tree.children(c[1]);
+
+            if d.is_empty() {
+                break;
+            }
+
+            comma = true;
+            node = d[1];
+        }
+
+        Ok((items, comma))
+    }
+
+    // LIST_D -> , LIST_E | e, depois que o primeiro item ja foi lido
+    fn comma_list_tail<T>(
+        &self,
+        node: usize,
+        item: &dyn Fn(usize) -> Result<T, CompilationError>
+    ) -> Result<(Vec<T>, bool), CompilationError> {
+        match self.tree.children(node).get(1) {
+            Some(&rest) => Ok((self.comma_list(rest, item)?.0, true)),
+            None => Ok((Vec::new(), false))
+        }
+    }
+
+    fn tuple_or_single(&self, elts: Vec<Expr>, tuple: bool) -> Expr {
+        let mut elts = elts;
+
+        if !tuple {
+            return elts.pop().unwrap();
+        }
+
+        let (row, col) = (elts[0].row, elts[0].col);
+        let (end_row, end_col) = end(elts.last().unwrap());
+
+        Expr {
+            kind: ExprKind::Tuple(elts),
+            row,
+            col,
+            end_row,
+            end_col
+        }
+    }
+
+    fn subscript_list(&self, node: usize) -> Result<Expr, CompilationError> {
+        let (elts, tuple) = self.comma_list(node, &|n| self.subscript(n))?;
+        Ok(self.tuple_or_single(elts, tuple))
+    }
+
+    fn target_list(&self, node: usize) -> Result<Expr, CompilationError> {
+        let target = self.star_expressions(node)?;
+
+        check_target(&target)?;
+        Ok(target)
+    }
+
+    // STAR_EXPRESSIONS, TARGET_LIST e SUBJECT: itens com * opcional, tupla se houver virgula
+    fn star_expressions(&self, node: usize) -> Result<Expr, CompilationError> {
+        let (elts, tuple) = self.comma_list(node, &|n| self.star_or_expression(n))?;
+        Ok(self.tuple_or_single(elts, tuple))
+    }
+
+    // X -> EXPRESSION | * BITWISE_OR
+    fn star_or_expression(&self, node: usize) -> Result<Expr, CompilationError> {
+        let c = self.tree.children(node);
+
+        match self.terminal(c[0]) {
+            Some(TkType::Operator("OPERATOR{VEZES}")) => {
+                let value = self.expression(c[1])?;
+                let end = end(&value);
+
+                Ok(self.expr_to(ExprKind::Starred(Box::new(value)), self.tree.token(c[0]), end))
+            },
+            _ => self.expression(c[0])
+        }
+    }
+
+    // TESTLIST_COMPL -> COMP_FOR | TESTLIST_COMP_D
+    fn testlist_compl(&self, first: Expr, node: usize) -> Result<TestList, CompilationError> {
+        let c = self.tree.children(node);
+
+        if self.tree.state(c[0]) == PossibleStates::COMP_FOR {
+            if let ExprKind::Starred(_) = first.kind {
+                return Err(expr_error(ErrorCode::InvalidUnpacking, &first, "iterable unpacking cannot be used in comprehension"));
+            }
+
+            return Ok((vec![first], false, Some(self.comprehension(c[0])?)));
+        }
+
+        let (mut elts, comma) = self.comma_list_tail(c[0], &|n| self.star_or_expression(n))?;
+        elts.insert(0, first);
+
+        Ok((elts, comma, None))
+    }
+
+    fn comprehension(&self, node: usize) -> Result<Vec<Comprehension>, CompilationError> {
+        let mut generators: Vec<Comprehension> = Vec::new();
+        let mut node = node;
+
+        loop {
+            let c = self.tree.children(node);
+
+            match self.tree.state(node) {
+                PossibleStates::COMP_FOR => {
+                    // async for ...: o resto fica uma posicao para a direita
+                    let is_async = self.terminal(c[0]) == Some(TkType::ReservedWord("RWORD{ASYNC}"));
+                    let c = if is_async { &c[1..] } else { c };
+
+                    generators.push(Comprehension {
+                        target: self.target_list(c[1])?,
+                        iter: self.expression(c[3])?,
+                        ifs: Vec::new(),
+                        is_async
+                    });
+                    node = c[4];
+                },
+                PossibleStates::COMP_IF => {
+                    let test = self.expression(c[1])?;
+                    generators.last_mut().unwrap().ifs.push(test);
+                    node = c[2];
+                },
+                _ => match c.first() {
+                    Some(&next) => node = next,
+                    None => return Ok(generators)
+                }
+            }
+        }
+    }
+
+    // YIELD_EXPRESSION -> yield YIELD_ARGUMENT_E
+    fn yield_expression(&self, c: &[usize]) -> Result<Expr, CompilationError> {
+        let tk = self.tree.token(c[0]);
+        let argument = self.tree.children(c[1]);
+        let yield_from = argument.len() == 2;
+
+        match self.frames.borrow_mut().last_mut() {
+            Some(Frame::Function { is_async: true, .. }) if yield_from => {
+                return Err(error(ErrorCode::InvalidContext, tk, "'yield from' inside async function"));
+            },
+            Some(Frame::Function { is_generator, .. }) => *is_generator = true,
+            _ => return Err(error(ErrorCode::InvalidContext, tk, "'yield' outside function"))
+        }
+
+        let kind = match *argument {
+            [_, value] => ExprKind::YieldFrom(Box::new(self.expression(value)?)),
+            [value] => ExprKind::Yield(Some(Box::new(self.expression_list(value)?))),
+            _ => return Ok(self.expr(ExprKind::Yield(None), tk))
+        };
+
+        Ok(self.expr_to(kind, tk, self.end_of(c[1])))
+    }
+
+    fn display(&self, tk: &Token, node: usize, end: (usize, usize)) -> Result<Expr, CompilationError> {
+        let c = self.tree.children(node);
+
+        if let Some(&value) = c.first() {
+            if self.tree.state(value) == PossibleStates::YIELD_EXPRESSION {
+                return self.expression(value);
+            }
+        }
+
+        let (elts, comma, generators) = match c.first() {
+            Some(&testlist) => {
+                let t = self.tree.children(testlist);
+                let first = self.star_or_expression(t[0])?;
+                self.testlist_compl(first, t[1])?
+            },
+            None => (Vec::new(), true, None)
+        };
+
+        let mut elts = elts;
+
+        let kind = match (tk.tk_type, generators) {
+            (TkType::Operator("OPERATOR{PARENTESES_ESQUERDO}"), Some(generators)) => ExprKind::GeneratorExp {
+                elt: Box::new(elts.pop().unwrap()),
+                generators
+            },
+            (TkType::Operator("OPERATOR{PARENTESES_ESQUERDO}"), None) => {
+                if !comma {
+                    let value = elts.pop().unwrap();
+
+                    if let ExprKind::Starred(_) = value.kind {
+                        return Err(expr_error(ErrorCode::InvalidUnpacking, &value, "cannot use starred expression here"));
+                    }
+
+                    return Ok(value);
+                }
+
+                ExprKind::Tuple(elts)
+            },
+            (_, Some(generators)) => {
+                self.check_async_comprehension(tk, &generators)?;
+
+                ExprKind::ListComp {
+                    elt: Box::new(elts.pop().unwrap()),
+                    generators
+                }
+            },
+            (_, None) => ExprKind::List(elts)
+        };
+
+        let display = self.expr_to(kind, tk, end);
+        self.check_comprehension(&display)?;
+
+        Ok(display)
+    }
+
+    fn dict_or_set(&self, tk: &Token, node: usize, end: (usize, usize)) -> Result<Expr, CompilationError> {
+        let c = match self.tree.children(node).first() {
+            Some(&items) => self.tree.children(items),
+            None => return Ok(self.expr_to(ExprKind::Dict {
+                keys: Vec::new(),
+                values: Vec::new()
+            }, tk, end))
+        };
+
+        let (key, value, tail) = match self.terminal(c[0]) {
+            Some(TkType::Operator("OPERATOR{NOME_PARAMETRO}")) => (None, self.expression(c[1])?, c[2]),
+            Some(TkType::Operator("OPERATOR{VEZES}")) => {
+                let value = self.star_or_expression(self.tree.children(node)[0])?;
+                let (elts, _, _) = self.testlist_compl(value, c[2])?;
+                return Ok(self.expr_to(ExprKind::Set(elts), tk, end));
+            },
+            _ => {
+                let mut first = self.expression(c[0])?;
+                let mut l = self.tree.children(c[1]);
+
+                if self.terminal(l[0]) == Some(TkType::Operator("OPERATOR{MORSA}")) {
+                    first = self.named_expression(first, l[1])?;
+                    l = &l[2..];
+                }
+
+                if self.tree.state(l[0]) == PossibleStates::TESTLIST_COMPL {
+                    let (mut elts, _, generators) = self.testlist_compl(first, l[0])?;
+
+                    let kind = match generators {
+                        Some(generators) => {
+                            self.check_async_comprehension(tk, &generators)?;
+
+                            ExprKind::SetComp {
+                                elt: Box::new(elts.pop().unwrap()),
+                                generators
+                            }
+                        },
+                        None => ExprKind::Set(elts)
+                    };
+
+                    let display = self.expr_to(kind, tk, end);
+                    self.check_comprehension(&display)?;
+
+                    return Ok(display);
+                }
+
+                (Some(first), self.expression(l[1])?, l[2])
+            }
+        };
+
+        let t = self.tree.children(tail);
+
+        if self.tree.state(t[0]) == PossibleStates::COMP_FOR {
+            let key = match key {
+                Some(key) => key,
+                None => return Err(error(ErrorCode::InvalidUnpacking, self.tree.token(c[0]), "dict unpacking cannot be used in dict comprehension"))
+            };
+
+            let generators = self.comprehension(t[0])?;
+            self.check_async_comprehension(tk, &generators)?;
+
+            let display = self.expr_to(ExprKind::DictComp {
+                key: Box::new(key),
+                value: Box::new(value),
+                generators
+            }, tk, end);
+
+            self.check_comprehension(&display)?;
+            return Ok(display);
+        }
+
+        let (items, _) = self.comma_list_tail(t[0], &|n| {
+            let i = self.tree.children(n);
+
+            match self.terminal(i[0]) {
+                Some(TkType::Operator("OPERATOR{NOME_PARAMETRO}")) => Ok((None, self.expression(i[1])?)),
+                _ => Ok((Some(self.expression(i[0])?), self.expression(i[2])?))
+            }
+        })?;
+
+        let mut keys = vec![key];
+        let mut values = vec![value];
+
+        for (k, v) in items {
+            keys.push(k);
+            values.push(v);
+        }
+
+        Ok(self.expr_to(ExprKind::Dict {
+            keys,
+            values
+        }, tk, end))
+    }
+
+    fn subscript(&self, node: usize) -> Result<Expr, CompilationError> {
+        let c = self.tree.children(node);
+
+        if self.tree.state(c[0]) == PossibleStates::SLICE {
+            return self.slice(None, c[0]);
+        }
+
+        let value = self.expression(c[0])?;
+
+        match self.tree.children(c[1]).first() {
+            Some(&slice) => {
+                if let ExprKind::NamedExpr { .. } = value.kind {
+                    return Err(error(ErrorCode::InvalidSyntax, self.tree.token(self.tree.children(slice)[0]), "invalid syntax"));
+                }
+
+                self.slice(Some(value), slice)
+            },
+            None => Ok(value)
+        }
+    }
+
+    // SLICE -> : SLICE_BOUND SLICE_STEP
+    fn slice(&self, lower: Option<Expr>, node: usize) -> Result<Expr, CompilationError> {
+        let c = self.tree.children(node);
+        let tk = self.tree.token(c[0]);
+        let upper = self.slice_bound(c[1])?;
+        let step = match self.tree.children(c[2]).get(1) {
+            Some(&bound) => self.slice_bound(bound)?,
+            None => None
+        };
+        let (row, col) = match &lower {
+            Some(lower) => (lower.row, lower.col),
+            None => (tk.row, tk.col)
+        };
+        let (end_row, end_col) = self.end_of(node);
+
+        Ok(Expr {
+            kind: ExprKind::Slice {
+                lower: lower.map(Box::new),
+                upper,
+                step
+            },
+            row,
+            col,
+            end_row,
+            end_col
+        })
+    }
+
+    fn slice_bound(&self, node: usize) -> Result<Option<Box<Expr>>, CompilationError> {
+        match self.tree.children(node).first() {
+            Some(&e) => Ok(Some(Box::new(self.expression(e)?))),
+            None => Ok(None)
+        }
+    }
+}
+
+// LIST -> ITEM LIST_D; LIST_D -> , LIST | e
+fn continue_list(node: &mut usize, d: usize, tree: &ParseTree) {
+    match tree.children(d).get(1) {
+        Some(&next) => *node = next,
+        None => *node = d
+    }
+}
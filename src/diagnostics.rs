@@ -0,0 +1,551 @@
+// Mensagens de erro no estilo do rustc: cabecalho, arquivo:linha:coluna, a linha do
+// fonte e marcas embaixo dos tokens. As posicoes guardadas sao as do lexer (a partir
+// de 0), so a renderizacao soma 1.
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+// os codigos sao estaveis: cada um tem o seu numero, nunca renumera nem reaproveita
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCode {
+    InvalidCharacter = 1,
+    UnterminatedString = 2,
+    InvalidLiteral = 3,
+    InvalidOperator = 4,
+    MismatchedBracket = 5,
+    UnmatchedBracket = 6,
+    UnclosedBracket = 7,
+    InconsistentDedent = 8,
+    UnexpectedToken = 9,
+    UnexpectedEof = 10,
+    InvalidSyntax = 11,
+    DuplicateArgument = 12,
+    UnsupportedFeature = 13,
+    TooDeeplyNested = 14,
+    InvalidTarget = 15,
+    InvalidContext = 16,
+    InvalidDeclaration = 17,
+    InvalidPattern = 18,
+    InvalidArguments = 19,
+    InvalidUnpacking = 20
+}
+
+// ordem em que o explain lista os codigos
+pub const ERROR_CODES: [ErrorCode; 20] = [
+    ErrorCode::InvalidCharacter,
+    ErrorCode::UnterminatedString,
+    ErrorCode::InvalidLiteral,
+    ErrorCode::InvalidOperator,
+    ErrorCode::MismatchedBracket,
+    ErrorCode::UnmatchedBracket,
+    ErrorCode::UnclosedBracket,
+    ErrorCode::InconsistentDedent,
+    ErrorCode::UnexpectedToken,
+    ErrorCode::UnexpectedEof,
+    ErrorCode::InvalidSyntax,
+    ErrorCode::DuplicateArgument,
+    ErrorCode::UnsupportedFeature,
+    ErrorCode::TooDeeplyNested,
+    ErrorCode::InvalidTarget,
+    ErrorCode::InvalidContext,
+    ErrorCode::InvalidDeclaration,
+    ErrorCode::InvalidPattern,
+    ErrorCode::InvalidArguments,
+    ErrorCode::InvalidUnpacking
+];
+
+impl ErrorCode {
+    pub fn id(self) -> String {
+        format!("PP{:04}", self as u32)
+    }
+
+    pub fn summary(self) -> &'static str {
+        match self {
+            ErrorCode::InvalidCharacter => "invalid character in source",
+            ErrorCode::UnterminatedString => "unterminated string literal",
+            ErrorCode::InvalidLiteral => "invalid numeric literal",
+            ErrorCode::InvalidOperator => "invalid operator",
+            ErrorCode::MismatchedBracket => "closing bracket does not match the opening one",
+            ErrorCode::UnmatchedBracket => "closing bracket without an opening one",
+            ErrorCode::UnclosedBracket => "bracket never closed",
+            ErrorCode::InconsistentDedent => "unindent does not match any outer indentation level",
+            ErrorCode::UnexpectedToken => "unexpected token",
+            ErrorCode::UnexpectedEof => "unexpected end of file",
+            ErrorCode::InvalidSyntax => "invalid construct",
+            ErrorCode::DuplicateArgument => "duplicate argument in function definition",
+            ErrorCode::UnsupportedFeature => "construct not supported by the target python version",
+            ErrorCode::TooDeeplyNested => "too many nested parentheses or expressions",
+            ErrorCode::InvalidTarget => "invalid target for assignment or deletion",
+            ErrorCode::InvalidContext => "'await', 'yield' or async construct outside of a function that allows it",
+            ErrorCode::InvalidDeclaration => "invalid global or nonlocal declaration",
+            ErrorCode::InvalidPattern => "invalid pattern in a case clause",
+            ErrorCode::InvalidArguments => "invalid order or form of parameters or call arguments",
+            ErrorCode::InvalidUnpacking => "starred expression or unpacking where it is not allowed"
+        }
+    }
+
+    // PP0005, pp0005 ou so 5
+    pub fn from_id(id: &str) -> Option<ErrorCode> {
+        let digits = id.trim_start_matches(&['P', 'p'][..]);
+        let number: u32 = digits.parse().ok()?;
+
+        ERROR_CODES.iter().find(|&&c| c as u32 == number).copied()
+    }
+
+    // descricao longa, exemplo e como corrigir, usados pelo explain
+    pub fn explanation(self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            ErrorCode::InvalidCharacter => (
+                "The source contains a character that does not start any python token, such as '$', '?' or\na non-ASCII symbol outside of a string or comment.",
+                "total = price $ 2",
+                "Remove the character or replace it with the intended operator. Characters like this are only\nallowed inside strings and comments."
+            ),
+            ErrorCode::UnterminatedString => (
+                "A string literal was opened but the line ended before the closing quote. Strings delimited by\na single quote character can't span lines.",
+                "message = \"hello",
+                "Add the missing closing quote, using the same quote character that opened the string."
+            ),
+            ErrorCode::InvalidLiteral => (
+                "A number is immediately followed by letters or other characters that can't be part of it.",
+                "width = 10px",
+                "Remove the suffix, or separate it with an operator or a space if it was meant to be a name."
+            ),
+            ErrorCode::InvalidOperator => (
+                "A sequence of operator characters does not form any python operator.",
+                "if not a ! b:",
+                "Use a valid operator, for example '!=' for inequality or 'not' for negation."
+            ),
+            ErrorCode::MismatchedBracket => (
+                "A closing bracket does not match the innermost bracket that is still open. The diagnostic also\npoints at the opening bracket.",
+                "values = [1, 2)",
+                "Close the innermost bracket with its matching character: ')' for '(', ']' for '[' and '}' for '{'."
+            ),
+            ErrorCode::UnmatchedBracket => (
+                "A closing bracket appears without any open bracket before it.",
+                "x = 1)",
+                "Remove the extra bracket or add the missing opening one."
+            ),
+            ErrorCode::UnclosedBracket => (
+                "The file ended while a bracket was still open. The diagnostic points at the bracket that was\nnever closed; the error may be far from where the closing bracket was forgotten.",
+                "call(1,\n     2",
+                "Add the closing bracket where the expression ends."
+            ),
+            ErrorCode::InconsistentDedent => (
+                "A line is indented less than the previous block but its indentation does not match any of\nthe enclosing blocks.",
+                "if ok:\n        run()\n    stop()",
+                "Indent the line exactly like one of the enclosing blocks, using the same amount of spaces or tabs."
+            ),
+            ErrorCode::UnexpectedToken => (
+                "The parser found a token that can't appear at this point. The message lists the tokens that\nwould have been accepted and the construct that came before.",
+                "def f(x) return x",
+                "Add the missing token, usually a ':', ',' or a closing bracket, or remove the extra one."
+            ),
+            ErrorCode::UnexpectedEof => (
+                "The file ended in the middle of a statement or block.",
+                "if ready:",
+                "Complete the statement, or add the body of the block that was opened on the last lines."
+            ),
+            ErrorCode::InvalidSyntax => (
+                "The tokens form a valid shape but python does not allow the construct in this position and\nnone of the more specific codes applies.",
+                "x[i := 0:2]",
+                "Follow the message; usually the construct needs parentheses or must be moved to its own statement."
+            ),
+            ErrorCode::DuplicateArgument => (
+                "The same name is used for two parameters of a function or lambda.",
+                "def f(a, a):\n    pass",
+                "Rename one of the parameters."
+            ),
+            ErrorCode::UnsupportedFeature => (
+                "The construct is valid python but newer than the version chosen with --target.",
+                "# with --target 3.6\nif (n := len(items)) > 3:\n    pass",
+                "Rewrite the code without the construct or raise the target; the min-version command lists\nthe version each construct needs."
+            ),
+            ErrorCode::TooDeeplyNested => (
+                "Brackets or expressions are nested deeper than the parser supports: 200 open brackets,\n500 unary operators, conditional expressions and lambdas inside each other, or 5000\nlevels in the resulting tree (a chain like a + b + c nests one level per operator).",
+                // o 201o parentese ja e o erro, o resto da expressao nem e lido
+                "x = ((((((((((((((((((((((((((((((((((((((((((((((((((\n    ((((((((((((((((((((((((((((((((((((((((((((((((((\n    ((((((((((((((((((((((((((((((((((((((((((((((((((\n    ((((((((((((((((((((((((((((((((((((((((((((((((((\n    (1",
+                "Split the expression into intermediate variables."
+            ),
+            ErrorCode::InvalidTarget => (
+                "The left side of an assignment, augmented assignment, annotation, assignment expression, 'for'\nor 'del' is not something a value can be stored in, such as a literal, a call or an operation.",
+                "1 = x",
+                "Assign only to names, attributes, subscripts, and lists or tuples of them."
+            ),
+            ErrorCode::InvalidContext => (
+                "'yield' and 'await' are only allowed inside a function, and 'await', 'async for', 'async with'\nand async comprehensions only inside an 'async def'. An async generator can't return a value.",
+                "def f():\n    await g()",
+                "Move the code into a function, or declare the function with 'async def'."
+            ),
+            ErrorCode::InvalidDeclaration => (
+                "A 'global' or 'nonlocal' declaration conflicts with how the name is used: it is a parameter,\nit is declared both ways, it is used before the declaration, or a 'nonlocal' name is not bound\nin any enclosing function.",
+                "def f(x):\n    global x",
+                "Put the declaration at the start of the function and use a name that is not a parameter; for\n'nonlocal', assign the name in an enclosing function first."
+            ),
+            ErrorCode::InvalidPattern => (
+                "A pattern of a 'case' clause is not allowed: it captures the same name twice, the alternatives\nof an or-pattern bind different names, a wildcard makes later patterns unreachable, or it\nuses an expression that is not a literal or attribute lookup.",
+                "match p:\n    case (x, x):\n        pass",
+                "Use a different name for each capture, bind the same names in every alternative and move\ncatch-all patterns to the last case."
+            ),
+            ErrorCode::InvalidArguments => (
+                "The parameters of a function or the arguments of a call are in an order python does not accept,\nsuch as a positional argument after a keyword one or a parameter without default after one with.",
+                "f(a=1, 2)",
+                "Put positional arguments before keyword arguments, and parameters with defaults after the\nones without."
+            ),
+            ErrorCode::InvalidUnpacking => (
+                "A starred expression or '**' unpacking is used where python can't unpack, such as alone as a\nvalue or as the element of a comprehension.",
+                "x = *a",
+                "Wrap the starred expression in a list or tuple, for example 'x = [*a]' or 'x = *a,'."
+            )
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Human,
+    Json,
+    Sarif
+}
+
+#[derive(Debug)]
+pub struct Label {
+    pub row: usize,
+    pub col: usize,
+    pub len: usize,
+    pub message: String
+}
+
+// machine-applicable so quando a correcao deixa o arquivo inteiro valido e nenhuma outra deixa
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Applicability {
+    MachineApplicable,
+    MaybeIncorrect
+}
+
+impl Applicability {
+    pub fn name(self) -> &'static str {
+        match self {
+            Applicability::MachineApplicable => "machine-applicable",
+            Applicability::MaybeIncorrect => "maybe-incorrect"
+        }
+    }
+}
+
+// troca len caracteres a partir de row:col por replacement, len 0 e uma insercao
+#[derive(Debug)]
+pub struct Suggestion {
+    pub message: String,
+    pub row: usize,
+    pub col: usize,
+    pub len: usize,
+    pub replacement: String,
+    pub applicability: Applicability
+}
+
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub code: ErrorCode,
+    pub message: String,
+    pub primary: Label,
+    pub secondary: Vec<Label>,
+    pub suggestions: Vec<Suggestion>
+}
+
+impl Diagnostic {
+    pub fn new(code: ErrorCode, message: &str, row: usize, col: usize, len: usize) -> Diagnostic {
+        Diagnostic {
+            code,
+            message: message.to_owned(),
+            primary: Label {
+                row,
+                col,
+                len: len.max(1),
+                message: String::new()
+            },
+            secondary: Vec::new(),
+            suggestions: Vec::new()
+        }
+    }
+
+    // marca outro trecho do fonte, como o parentese que abriu o bloco
+    pub fn with_label(mut self, row: usize, col: usize, len: usize, message: &str) -> Diagnostic {
+        self.secondary.push(Label {
+            row,
+            col,
+            len: len.max(1),
+            message: message.to_owned()
+        });
+
+        self
+    }
+
+    pub fn with_suggestion(mut self, suggestion: Suggestion) -> Diagnostic {
+        self.suggestions.push(suggestion);
+        self
+    }
+
+    // linha:coluna contando a partir de 1
+    pub fn location(&self) -> String {
+        format!("{}:{}", self.primary.row + 1, self.primary.col + 1)
+    }
+
+    pub fn render(&self, path: &str, source: &str, colour: bool) -> String {
+        let paint = |style: &str, text: &str| -> String {
+            if colour {
+                format!("{}{}{}", style, text, RESET)
+            } else {
+                text.to_owned()
+            }
+        };
+
+        let lines: Vec<&str> = source.lines().collect();
+        let mut labels: Vec<(&Label, bool)> = vec![(&self.primary, true)];
+        labels.extend(self.secondary.iter().map(|l| (l, false)));
+
+        let mut rows: Vec<usize> = labels.iter().map(|(l, _)| l.row).collect();
+        rows.sort_unstable();
+        rows.dedup();
+
+        let width = (rows[rows.len() - 1] + 1).to_string().len();
+        let gutter = |text: &str| format!("{} {}", " ".repeat(width), paint(BLUE, text));
+
+        let header = format!("error[{}]", self.code.id());
+        let mut out = format!("{}{}\n", paint(RED, &header), paint(BOLD, &format!(": {}", self.message)));
+        out.push_str(&format!("{}{} {}:{}\n", " ".repeat(width), paint(BLUE, "-->"), path, self.location()));
+        out.push_str(&gutter("|"));
+        out.push('\n');
+
+        let mut previous: Option<usize> = None;
+
+        for row in rows {
+            if previous.is_some_and(|p| row > p + 1) {
+                out.push_str(&paint(BLUE, "..."));
+                out.push('\n');
+            }
+
+            previous = Some(row);
+
+            let text = lines.get(row).copied().unwrap_or_default();
+            let chars: Vec<char> = text.chars().collect();
+            let number = format!("{:>width$} |", row + 1, width = width);
+            out.push_str(&format!("{} {}\n", paint(BLUE, &number), text));
+
+            let mut here: Vec<&(&Label, bool)> = labels.iter().filter(|(l, _)| l.row == row).collect();
+            here.sort_by_key(|(l, _)| l.col);
+
+            // todas as marcas numa linha, a mensagem da ultima logo depois dela
+            let (last, last_primary) = here[here.len() - 1];
+            let mut painted = String::new();
+            let mut column = 0;
+
+            for (label, primary) in here.iter() {
+                let style = if *primary { RED } else { BLUE };
+                let symbol = if *primary { "^" } else { "-" };
+
+                painted.push_str(&padding(&chars, column, label.col));
+                painted.push_str(&paint(style, &symbol.repeat(span_width(&chars, label.col, label.col + label.len))));
+                column = label.col.max(column) + label.len;
+            }
+
+            if !last.message.is_empty() {
+                let style = if *last_primary { RED } else { BLUE };
+                painted.push_str(&format!(" {}", paint(style, &last.message)));
+            }
+
+            out.push_str(&format!("{} {}\n", gutter("|"), painted));
+
+            // as outras mensagens descem por uma barra, da direita para a esquerda
+            for (label, primary) in here[..here.len() - 1].iter().rev() {
+                if label.message.is_empty() {
+                    continue;
+                }
+
+                let style = if *primary { RED } else { BLUE };
+                let indent = padding(&chars, 0, label.col);
+
+                out.push_str(&format!("{} {}{}\n", gutter("|"), indent, paint(style, "|")));
+                out.push_str(&format!("{} {}{}\n", gutter("|"), indent, paint(style, &label.message)));
+            }
+        }
+
+        for suggestion in self.suggestions.iter() {
+            out.push_str(&format!("{} {}\n", gutter("="), paint(BOLD, &format!("help: {}", suggestion.message))));
+        }
+
+        out
+    }
+}
+
+// colunas que o caractere ocupa no terminal: as letras do CJK e os emoji ocupam duas e os acentos
+// combinantes nenhuma
+fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x0300..=0x036F | 0x200B..=0x200F => 0,
+        0x1100..=0x115F | 0x2E80..=0x303E | 0x3041..=0x33FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF |
+        0xA000..=0xA4CF | 0xAC00..=0xD7A3 | 0xF900..=0xFAFF | 0xFE30..=0xFE4F | 0xFF00..=0xFF60 |
+        0xFFE0..=0xFFE6 | 0x1F300..=0x1F64F | 0x1F900..=0x1F9FF | 0x20000..=0x3FFFD => 2,
+        _ => 1
+    }
+}
+
+// espaco ate a coluna to (contada em caracteres, como no lexer) com a largura do proprio texto:
+// tabs continuam tabs, para as marcas ficarem embaixo do que apontam
+fn padding(line: &[char], from: usize, to: usize) -> String {
+    (from..to).map(|i| match line.get(i) {
+        Some('\t') => "\t".to_owned(),
+        Some(&c) => " ".repeat(char_width(c)),
+        None => " ".to_owned()
+    }).collect()
+}
+
+fn span_width(line: &[char], from: usize, to: usize) -> usize {
+    (from..to).map(|i| line.get(i).map_or(1, |&c| char_width(c))).sum()
+}
+
+// escapa o texto para uma string JSON
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c)
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+// inicio e fim (exclusivo) contando a partir de 1
+fn json_span(row: usize, col: usize, len: usize) -> String {
+    format!(
+        "{{\"start\":{{\"line\":{},\"column\":{}}},\"end\":{{\"line\":{},\"column\":{}}}}}",
+        row + 1, col + 1, row + 1, col + len + 1
+    )
+}
+
+pub fn to_json(diagnostics: &[Diagnostic], path: &str) -> String {
+    let items: Vec<String> = diagnostics.iter().map(|d| {
+        let labels: Vec<String> = d.secondary.iter()
+            .map(|l| format!("{{\"message\":{},\"span\":{}}}", escape(&l.message), json_span(l.row, l.col, l.len)))
+            .collect();
+        let suggestions: Vec<String> = d.suggestions.iter()
+            .map(|s| format!(
+                "{{\"message\":{},\"span\":{},\"replacement\":{},\"applicability\":{}}}",
+                escape(&s.message), json_span(s.row, s.col, s.len), escape(&s.replacement), escape(s.applicability.name())
+            ))
+            .collect();
+
+        format!(
+            "{{\"code\":{},\"severity\":\"error\",\"message\":{},\"file\":{},\"span\":{},\"labels\":[{}],\"suggestions\":[{}]}}",
+            escape(&d.code.id()), escape(&d.message), escape(path), json_span(d.primary.row, d.primary.col, d.primary.len),
+            labels.join(","), suggestions.join(",")
+        )
+    }).collect();
+
+    format!("[{}]", items.join(","))
+}
+
+fn sarif_region(row: usize, col: usize, len: usize) -> String {
+    format!(
+        "{{\"startLine\":{},\"startColumn\":{},\"endLine\":{},\"endColumn\":{}}}",
+        row + 1, col + 1, row + 1, col + len + 1
+    )
+}
+
+// o SARIF pede uma URI: caminho relativo vira referencia relativa, absoluto vira file://,
+// e o resto e codificado com %XX
+fn artifact_uri(path: &str) -> String {
+    let mut uri = String::new();
+
+    if path.starts_with('/') {
+        uri.push_str("file://");
+    }
+
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => uri.push(byte as char),
+            _ => uri.push_str(&format!("%{:02X}", byte))
+        }
+    }
+
+    uri
+}
+
+fn sarif_location(label: &Label, path: &str) -> String {
+    format!(
+        "{{\"artifactLocation\":{{\"uri\":{}}},\"region\":{}}}",
+        escape(&artifact_uri(path)), sarif_region(label.row, label.col, label.len)
+    )
+}
+
+// SARIF 2.1.0, um run com todas as regras e um result por diagnostico
+pub fn to_sarif(diagnostics: &[Diagnostic], path: &str) -> String {
+    let rules: Vec<String> = ERROR_CODES.iter()
+        .map(|c| format!("{{\"id\":{},\"shortDescription\":{{\"text\":{}}}}}", escape(&c.id()), escape(c.summary())))
+        .collect();
+
+    let results: Vec<String> = diagnostics.iter().map(|d| {
+        let related: Vec<String> = d.secondary.iter().enumerate()
+            .map(|(i, l)| format!(
+                "{{\"id\":{},\"physicalLocation\":{},\"message\":{{\"text\":{}}}}}",
+                i, sarif_location(l, path), escape(&l.message)
+            ))
+            .collect();
+
+        // regiao vazia (fim igual ao inicio) e uma insercao; o SARIF nao tem applicability, vai no property bag
+        let fixes: Vec<String> = d.suggestions.iter()
+            .map(|s| format!(
+                "{{\"description\":{{\"text\":{}}},\"artifactChanges\":[{{\"artifactLocation\":{{\"uri\":{}}},\"replacements\":[{{\"deletedRegion\":{},\"insertedContent\":{{\"text\":{}}}}}]}}],\"properties\":{{\"applicability\":{}}}}}",
+                escape(&s.message), escape(&artifact_uri(path)), sarif_region(s.row, s.col, s.len), escape(&s.replacement),
+                escape(s.applicability.name())
+            ))
+            .collect();
+
+        let index = ERROR_CODES.iter().position(|&c| c == d.code).unwrap();
+
+        format!(
+            "{{\"ruleId\":{},\"ruleIndex\":{},\"level\":\"error\",\"message\":{{\"text\":{}}},\"locations\":[{{\"physicalLocation\":{}}}],\"relatedLocations\":[{}],\"fixes\":[{}]}}",
+            escape(&d.code.id()), index, escape(&d.message), sarif_location(&d.primary, path), related.join(","), fixes.join(",")
+        )
+    }).collect();
+
+    // as colunas contam caracteres, nao unidades UTF-16 como o SARIF supoe por padrao
+    format!(
+        "{{\"$schema\":\"https://json.schemastore.org/sarif-2.1.0.json\",\"version\":\"2.1.0\",\"runs\":[{{\"tool\":{{\"driver\":{{\"name\":\"python-parser\",\"version\":{},\"rules\":[{}]}}}},\"columnKind\":\"unicodeCodePoints\",\"results\":[{}]}}]}}",
+        escape(env!("CARGO_PKG_VERSION")), rules.join(","), results.join(",")
+    )
+}
+
+// texto vai para a saida de erro, JSON e SARIF para a saida padrao
+pub fn emit(diagnostics: &[Diagnostic], format: Format, path: &str, source: &str, colour: bool) {
+    match format {
+        Format::Human => {
+            for d in diagnostics {
+                eprint!("{}", d.render(path, source, colour));
+            }
+        },
+        Format::Json => println!("{}", to_json(diagnostics, path)),
+        Format::Sarif => println!("{}", to_sarif(diagnostics, path))
+    }
+}
+
+// texto do comando explain
+pub fn explain(code: ErrorCode) -> String {
+    let (description, example, fix) = code.explanation();
+    let example: Vec<String> = example.lines().map(|l| format!("    {}", l)).collect();
+
+    format!(
+        "{}: {}\n\n{}\n\nExample:\n\n{}\n\nHow to fix:\n\n{}\n",
+        code.id(), code.summary(), description, example.join("\n"), fix
+    )
+}
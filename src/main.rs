@@ -1,21 +1,40 @@
 extern crate clap;
 
-use clap::{Arg, App};
+mod ast;
+mod diagnostics;
+mod version;
+#[cfg(test)]
+mod tests;
+
+use clap::{App, AppSettings, Arg, SubCommand};
 
 use std::fmt;
 use std::fs::File;
-use std::io::{BufRead, Write, BufReader, BufWriter};
-use std::collections::HashMap;
+use std::io::{IsTerminal, Write, BufWriter};
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+use diagnostics::{Applicability, Diagnostic, ErrorCode, Suggestion};
+
+use version::Version;
 
 enum CompilationError {
-    ParseError(String),
-    SintaxError(String)
+    SintaxError(Box<Diagnostic>)
+}
+
+fn syntax_error(code: ErrorCode, message: &str, row: usize, col: usize, len: usize) -> CompilationError {
+    CompilationError::SintaxError(Box::new(Diagnostic::new(code, message, row, col, len)))
 }
 
-static RESERVED_WORDS: [(&'static str, &'static str); 32] = [
+static RESERVED_WORDS: [(&str, &str); 37] = [
+    ("False", "RWORD{FALSE}"),
+    ("None", "RWORD{NONE}"),
+    ("True", "RWORD{TRUE}"),
     ("and", "RWORD{AND}"),
     ("as", "RWORD{AS}"),
     ("assert", "RWORD{ASSERT}"),
+    ("async", "RWORD{ASYNC}"),
+    ("await", "RWORD{AWAIT}"),
     ("break", "RWORD{BREAK}"),
     ("class", "RWORD{CLASS}"),
     ("continue", "RWORD{CONTINUE}"),
@@ -34,12 +53,11 @@ static RESERVED_WORDS: [(&'static str, &'static str); 32] = [
     ("in", "RWORD{IN}"),
     ("is", "RWORD{IS}"),
     ("lambda", "RWORD{LAMBDA}"),
-    ("none", "RWORD{NONE}"),
     ("nonlocal", "RWORD{NONLOCAL}"),
     ("not", "RWORD{NOT}"),
     ("or", "RWORD{OR}"),
     ("pass", "RWORD{PASS}"),
-    // ("print", "RWORD{PRINT}"),
+    ("print", "RWORD{PRINT}"),
     ("raise", "RWORD{RAISE}"),
     ("return", "RWORD{RETURN}"),
     ("try", "RWORD{TRY}"),
@@ -48,7 +66,34 @@ static RESERVED_WORDS: [(&'static str, &'static str); 32] = [
     ( "yield", "RWORD{YIELD}")
 ];
 
-static OPERATORS: [(&'static str, &'static str); 44] = [
+// palavras que so sao reservadas no python 2 ou so no python 3
+static PYTHON2_KEYWORDS: [&str; 2] = ["exec", "print"];
+static PYTHON3_KEYWORDS: [&str; 6] = ["False", "None", "True", "async", "await", "nonlocal"];
+
+fn is_reserved_word(lexema: &str, target: Version) -> bool {
+    // antes do 3.7 async e await so sao palavras reservadas em async def, ver mark_async_keywords
+    if (lexema == "async" || lexema == "await") && target < Version(3, 7) {
+        return false;
+    }
+
+    if target.is_python2() {
+        !PYTHON3_KEYWORDS.contains(&lexema)
+    } else {
+        !PYTHON2_KEYWORDS.contains(&lexema)
+    }
+}
+
+// ur"..." so existe no python 2
+static STRING_PREFIXES: [&str; 8] = ["r", "u", "b", "f", "br", "rb", "fr", "rf"];
+
+// so sao palavras reservadas no inicio de certos comandos, em qualquer outro lugar sao identificadores
+static SOFT_KEYWORDS: [(&str, &str); 3] = [
+    ("case", "RWORD{CASE}"),
+    ("match", "RWORD{MATCH}"),
+    ("type", "RWORD{TYPE}")
+];
+
+static OPERATORS: [(&str, &str); 47] = [
     ("+", "OPERATOR{MAIS}"),
     ("-", "OPERATOR{MENOS}"),
     ("*", "OPERATOR{VEZES}"),
@@ -92,54 +137,32 @@ static OPERATORS: [(&'static str, &'static str); 44] = [
     ("^=", "OPERATOR{CIRCUMFLEXO_IGUAL}"),
     (">>=", "OPERATOR{SHIFT_RIGHT_IGUAL}"),
     ("<<=", "OPERATOR{SHIFT_LEFT_IGUAL}"),
-    ("**=", "OPERATOR{DUPLO_ASTERISCO_IGUAL}")
+    ("**=", "OPERATOR{DUPLO_ASTERISCO_IGUAL}"),
+    ("...", "OPERATOR{RETICENCIAS}"),
+    ("->", "OPERATOR{SETA}"),
+    (":=", "OPERATOR{MORSA}")
 ];
 
 fn char_defines_operator(c: char) -> bool {
-    match c {
-        '+' => true,
-        '-' => true,
-        '*' => true,
-        '/' => true,
-        '%' => true,
-        '&' => true,
-        '|' => true,
-        '^' => true,
-        '~' => true,
-        '<' => true,
-        '>' => true,
-        '(' => true,
-        ')' => true,
-        '[' => true,
-        ']' => true,
-        '{' => true,
-        '}' => true,
-        ',' => true,
-        ':' => true,
-        '.' => true,
-        ';' => true,
-        '@' => true,
-        '=' => true,
-        _ => false
-    }
+    matches!(c,
+        '+' | '-' | '*' | '/' | '%' | '&' | '|' | '^' | '~' | '<' | '>' |
+        '(' | ')' | '[' | ']' | '{' | '}' | ',' | ':' | '.' | ';' | '@' | '=' | '!'
+    )
 }
 
 fn char_acts_as_separator(c: char) -> bool {
-    match c {
-        ' ' => true,
-        '\t' => true,
-        '\n' => true,
-        _ => false
-    }
+    matches!(c, ' ' | '\t' | '\n')
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 enum LiteralTypes {
     Int = 1,
     Float = 2,
-    String = 4
+    String = 4,
+    Imaginary = 8
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 enum TkType {
     Indentaion,
@@ -152,7 +175,7 @@ enum TkType {
     END
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 struct Token {
     tk_type: TkType,
     lexema: String,
@@ -163,10 +186,10 @@ struct Token {
 impl Token {
     fn new(tk_type: TkType, lexema: String, row: usize, col: usize) -> Token {
         Token {
-            tk_type: tk_type,
-            lexema: lexema.to_owned(),
-            row: row,
-            col: col
+            tk_type,
+            lexema,
+            row,
+            col
         }
     }
 }
@@ -182,6 +205,7 @@ impl fmt::Display for Token {
                 LiteralTypes::Int => "LITERAL{INT}",
                 LiteralTypes::Float => "LITERAL{FLOAT}",
                 LiteralTypes::String => "LITERAL{STRING}",
+                LiteralTypes::Imaginary => "LITERAL{IMAGINARY}",
             },
             TkType::Identifier => "ID",
             TkType::EOS => "EOS",
@@ -192,8 +216,32 @@ impl fmt::Display for Token {
     }
 }
 
+// nome do tipo de token como aparece nas mensagens de erro
+fn token_name(tk_type: TkType) -> String {
+    match tk_type {
+        TkType::ReservedWord(id) | TkType::Operator(id) => {
+            let lexema = RESERVED_WORDS.iter()
+                .chain(SOFT_KEYWORDS.iter())
+                .chain(OPERATORS.iter())
+                .find(|w| w.1 == id)
+                .map_or(id, |w| w.0);
+
+            format!("'{}'", lexema)
+        },
+        TkType::Literal(LiteralTypes::Int) => "integer".to_owned(),
+        TkType::Literal(LiteralTypes::Float) => "float".to_owned(),
+        TkType::Literal(LiteralTypes::String) => "string".to_owned(),
+        TkType::Literal(LiteralTypes::Imaginary) => "imaginary number".to_owned(),
+        TkType::Identifier => "identifier".to_owned(),
+        TkType::Indentaion => "indent".to_owned(),
+        TkType::Dedentation => "dedent".to_owned(),
+        TkType::EOS => "newline".to_owned(),
+        TkType::END => "end of file".to_owned()
+    }
+}
+
 // TODO passar um option com o char de indentacao do arquivo
-fn get_line_indentation(line: &Vec<char>) -> usize {
+fn get_line_indentation(line: &[char]) -> usize {
     let mut ind: usize = 0;
 
     for c in line.iter() {
@@ -207,11 +255,7 @@ fn get_line_indentation(line: &Vec<char>) -> usize {
     ind
 }
 
-fn get_string_literal(line: &Vec<char>, delimiter: char, col: usize, row: usize) -> Option<(Token, usize)> {
-    if line[col] != delimiter {
-        return None;
-    }
-    
+fn get_string_literal(line: &[char], delimiter: char, col: usize, row: usize) -> Result<(Token, usize), CompilationError> {
     let mut lexema = String::default();
     lexema.push(delimiter);
     let mut icol = col + 1;
@@ -220,8 +264,10 @@ fn get_string_literal(line: &Vec<char>, delimiter: char, col: usize, row: usize)
         lexema.push(line[icol]);
         
         match line[icol] {
-            // TODO tratar os erros igual gente decente
-            '\n' => panic!("Unexpected end of line at: row {}, col {}", row, icol),
+            '\n' => {
+                let message = format!("unterminated string literal (detected at line {})", row + 1);
+                return Err(syntax_error(ErrorCode::UnterminatedString, &message, row, col, 1));
+            },
             '\\' => {
                 if line[icol + 1] == delimiter {
                     lexema.push(delimiter);
@@ -241,18 +287,44 @@ fn get_string_literal(line: &Vec<char>, delimiter: char, col: usize, row: usize)
     }
 
     let token = Token::new(TkType::Literal(LiteralTypes::String), lexema, row, col);
-    Some((token, icol))
+    Ok((token, icol))
 }
 
-fn get_int_literal(line: &Vec<char>, col: usize, row: usize) -> Option<(Token, usize)> {
+// r"...", b'...', f"..." etc, o prefixo fica no lexema
+fn get_prefixed_string_literal(line: &[char], col: usize, row: usize) -> Result<Option<(Token, usize)>, CompilationError> {
+    let prefix: String = line[col..].iter().take_while(|c| c.is_ascii_alphabetic()).collect();
+    let delimiter = line[col + prefix.len()];
+
+    if delimiter != '\'' && delimiter != '"' {
+        return Ok(None);
+    }
+
+    if !STRING_PREFIXES.contains(&prefix.to_lowercase().as_str()) {
+        return Ok(None);
+    }
+
+    let (mut token, icol) = get_string_literal(line, delimiter, col + prefix.len(), row)?;
+    token.lexema.insert_str(0, &prefix);
+    token.col = col;
+
+    Ok(Some((token, icol)))
+}
+
+// 2j, 1.5J: o sufixo so vale no fim do numero
+fn is_imaginary_suffix(line: &[char], icol: usize) -> bool {
+    matches!(line[icol], 'j' | 'J') && (char_acts_as_separator(line[icol + 1]) || char_defines_operator(line[icol + 1]))
+}
+
+fn get_int_literal(line: &[char], col: usize, row: usize) -> Result<Option<(Token, usize)>, CompilationError> {
     let mut icol = col;
 
     if !line[icol].is_numeric() {
-        return None;
+        return Ok(None);
     }
     
     let mut lexema = String::default();
     lexema.push(line[col]);
+    let mut literal_type = LiteralTypes::Int;
     icol += 1;
 
     loop {
@@ -260,36 +332,36 @@ fn get_int_literal(line: &Vec<char>, col: usize, row: usize) -> Option<(Token, u
 
         if c.is_numeric() {
             lexema.push(c);
-        } else if char_acts_as_separator(c) {
+        } else if is_imaginary_suffix(line, icol) {
+            lexema.push(c);
+            literal_type = LiteralTypes::Imaginary;
+            icol += 1;
             break;
-        } else if char_defines_operator(c) {
+        } else if char_acts_as_separator(c) || char_defines_operator(c) {
             break;
         } else {
-            // TODO tratar os erros igual gente decente
-            panic!("Invalid literal at: row {}, col {}", row, col);
+            return Err(syntax_error(ErrorCode::InvalidLiteral, "invalid decimal literal", row, col, icol - col + 1));
         }
 
         icol += 1;
     }
 
-    if let Ok(_) = lexema.parse::<i32>() {
-        let token = Token::new(TkType::Literal(LiteralTypes::Int), lexema, row, col);
-        Some((token, icol))
-    } else {
-        None
-    }
+    // inteiros do python nao tem limite, o lexema fica como esta
+    let token = Token::new(TkType::Literal(literal_type), lexema, row, col);
+    Ok(Some((token, icol)))
 }
 
-fn get_float_literal(line: &Vec<char>, col: usize, row: usize) -> Option<(Token, usize)> {
+fn get_float_literal(line: &[char], col: usize, row: usize) -> Result<Option<(Token, usize)>, CompilationError> {
     let mut icol = col;
 
     if line[icol] != '.' && !line[icol].is_numeric() {
-        return None;
+        return Ok(None);
     }
     
     let mut lexema = String::default();
     lexema.push(line[icol]);
     let mut had_dot = line[icol] == '.';
+    let mut literal_type = LiteralTypes::Float;
     icol += 1;
 
     loop {
@@ -297,76 +369,79 @@ fn get_float_literal(line: &Vec<char>, col: usize, row: usize) -> Option<(Token,
 
         if c.is_numeric() {
             lexema.push(c);
+        } else if is_imaginary_suffix(line, icol) && lexema != "." {
+            lexema.push(c);
+            literal_type = LiteralTypes::Imaginary;
+            icol += 1;
+            break;
         } else if c == '.' && !had_dot {
             lexema.push('.');
             had_dot = true;
-        } else if char_acts_as_separator(c) {
-            break;
-        } else if char_defines_operator(c) {
+        } else if char_acts_as_separator(c) || char_defines_operator(c) {
             break;
         } else {
             if lexema == "." {
-                return None;
+                return Ok(None);
             }
-            
-            // TODO tratar os erros igual gente decente
-            panic!("Invalid literal at: row {}, col {}", row, col);
+
+            return Err(syntax_error(ErrorCode::InvalidLiteral, "invalid decimal literal", row, col, icol - col + 1));
         }
 
         icol += 1;
     }
 
-    if had_dot {
-        let token = Token::new(TkType::Literal(LiteralTypes::Float), lexema, row, col);
-        Some((token, icol))
+    // um '.' sozinho e o operador de acesso a atributo
+    if had_dot && lexema != "." {
+        let token = Token::new(TkType::Literal(literal_type), lexema, row, col);
+        Ok(Some((token, icol)))
     } else {
-        None
+        Ok(None)
     }
 }
 
-fn get_operator(line: &Vec<char>, col: usize, row: usize) -> Option<(Token, usize)> {
+fn get_operator(line: &[char], col: usize, row: usize) -> Result<Option<(Token, usize)>, CompilationError> {
     let mut icol = col;
 
     if !char_defines_operator(line[icol]) {
-        return None;
+        return Ok(None);
     }
 
     let mut lexema = String::default();
-    lexema.push(line[col]);
-    icol += 1;
+    let mut longest = (0, col);
 
-    loop {
-        let c = line[icol];
+    // continua enquanto o lexema for prefixo de algum operador, para que ".." chegue a "..."
+    while char_defines_operator(line[icol]) {
+        lexema.push(line[icol]);
+        icol += 1;
 
-        if char_defines_operator(c) {
-            lexema.push(c);
-        } else {
-            break;
+        if OPERATORS.iter().any(|op| op.0 == lexema) {
+            longest = (lexema.len(), icol);
         }
 
-        if !OPERATORS.iter().any(|op| op.0 == lexema) {
-            lexema.pop();
+        if !OPERATORS.iter().any(|op| op.0.starts_with(lexema.as_str())) {
             break;
         }
+    }
 
-        icol += 1;
+    if longest.0 > 0 {
+        lexema.truncate(longest.0);
+        icol = longest.1;
     }
 
     if !lexema.is_empty() {
         let id = match OPERATORS.iter().find(|op| op.0 == lexema) {
             Some(op) => op.1,
-            // TODO tratar os erros igual gente decente
-            None => panic!("Invalid operation at: row {}, col {}", row, col)
+            None => return Err(syntax_error(ErrorCode::InvalidOperator, "invalid syntax", row, col, lexema.chars().count()))
         };
 
         let token = Token::new(TkType::Operator(id), lexema, row, col);
-        Some((token, icol))
+        Ok(Some((token, icol)))
     } else {
-        None
+        Ok(None)
     }
 }
 
-fn get_reserved_word_or_identifier(line: &Vec<char>, col: usize, row: usize) -> Option<(Token, usize)> {
+fn get_reserved_word_or_identifier(line: &[char], col: usize, row: usize, target: Version) -> Option<(Token, usize)> {
     let mut icol = col;
 
     if !line[icol].is_ascii_alphabetic() && line[icol] != '_' {
@@ -384,7 +459,8 @@ fn get_reserved_word_or_identifier(line: &Vec<char>, col: usize, row: usize) ->
             break;
         }
 
-        if char_acts_as_separator(c) {
+        // x"..." nao e um prefixo valido, a string vira outro token
+        if char_acts_as_separator(c) || c == '\'' || c == '"' {
             break;
         }
 
@@ -392,7 +468,7 @@ fn get_reserved_word_or_identifier(line: &Vec<char>, col: usize, row: usize) ->
         icol += 1
     }
 
-    let tp = match RESERVED_WORDS.iter().find(|i| i.0 == lexema) {
+    let tp = match RESERVED_WORDS.iter().find(|i| i.0 == lexema && is_reserved_word(i.0, target)) {
         Some(i) => TkType::ReservedWord(i.1),
         None => TkType::Identifier,
     };
@@ -401,24 +477,193 @@ fn get_reserved_word_or_identifier(line: &Vec<char>, col: usize, row: usize) ->
     Some((token, icol))
 }
 
-fn generate_tokens(src_file: &str) -> std::io::Result<Vec<Token>> {
-    let mut tokens = Vec::new();
-    let mut src = BufReader::new(File::open(src_file)?);
+// inicio de cada statement de uma linha: depois de ; e depois do : de um comando composto com o
+// corpo na mesma linha, como em if x: type X = int
+fn statement_starts(line: &[Token]) -> Vec<usize> {
+    let mut starts = vec![0];
+    let mut brackets = 0;
+    let mut lambdas = 0;
+    let mut header = matches!(line[0].tk_type,
+        TkType::ReservedWord("RWORD{IF}") | TkType::ReservedWord("RWORD{ELIF}") | TkType::ReservedWord("RWORD{ELSE}") |
+        TkType::ReservedWord("RWORD{WHILE}") | TkType::ReservedWord("RWORD{FOR}") | TkType::ReservedWord("RWORD{TRY}") |
+        TkType::ReservedWord("RWORD{EXCEPT}") | TkType::ReservedWord("RWORD{FINALLY}") | TkType::ReservedWord("RWORD{WITH}") |
+        TkType::ReservedWord("RWORD{DEF}") | TkType::ReservedWord("RWORD{CLASS}") | TkType::ReservedWord("RWORD{ASYNC}") |
+        TkType::ReservedWord("RWORD{CASE}")
+    );
+
+    for (i, token) in line.iter().enumerate() {
+        match token.tk_type {
+            TkType::Operator("OPERATOR{PARENTESES_ESQUERDO}") |
+            TkType::Operator("OPERATOR{COLCHETES_ESQUERDO}") |
+            TkType::Operator("OPERATOR{CHAVES_ESQUERDA}") => brackets += 1,
+            TkType::Operator("OPERATOR{PARENTESES_DIREITO}") |
+            TkType::Operator("OPERATOR{COLCHETES_DIREITO}") |
+            TkType::Operator("OPERATOR{CHAVES_DIREITA}") => brackets -= 1,
+            _ if brackets > 0 => {},
+            TkType::ReservedWord("RWORD{LAMBDA}") => lambdas += 1,
+            // o : de uma lambda no cabecalho nao e o fim dele
+            TkType::Operator("OPERATOR{DOIS_PONTOS}") if lambdas > 0 => lambdas -= 1,
+            TkType::Operator("OPERATOR{DOIS_PONTOS}") if header => {
+                header = false;
+                starts.push(i + 1);
+            },
+            TkType::Operator("OPERATOR{PONTO_VIRGULA}") => starts.push(i + 1),
+            _ => {}
+        }
+    }
 
-    // TODO anotar o processo melhor
-    // TODO `log` seria uma boa ideia
-    println!("Tokenazing: \"{}\"", src_file);
+    starts.retain(|&s| s < line.len());
+    starts
+}
+
+// match x:, case <padrao>: dentro de um match e type X = ...
+fn mark_soft_keywords(tokens: &mut [Token]) {
+    let mut depth = 0;
+    // profundidade dos blocos que contem os case de um match
+    let mut match_blocks: Vec<usize> = Vec::new();
+    let mut start = 0;
+
+    while start < tokens.len() {
+        match tokens[start].tk_type {
+            TkType::Indentaion => {
+                depth += 1;
+                start += 1;
+                continue;
+            },
+            TkType::Dedentation => {
+                depth -= 1;
+
+                while match_blocks.last().is_some_and(|&d| d > depth) {
+                    match_blocks.pop();
+                }
+
+                start += 1;
+                continue;
+            },
+            _ => {}
+        }
+
+        let end = match tokens[start..].iter().position(|t| t.tk_type == TkType::EOS) {
+            Some(p) => start + p,
+            None => tokens.len()
+        };
+
+        // case marcado antes de procurar o fim do cabecalho dele
+        let mut statement = 0;
+        let mut starts = vec![0];
+
+        while statement < starts.len() {
+            let first = start + starts[statement];
+            let last = starts.get(statement + 1).map_or(end, |&s| start + s);
+            let line = &tokens[first..last];
+
+            let soft = match line[0].tk_type {
+                TkType::Identifier => SOFT_KEYWORDS.iter().find(|k| k.0 == line[0].lexema),
+                _ => None
+            };
+
+            let is_keyword = match soft {
+                // match e case so comecam a linha, depois de ; ou de um : ja sao nomes
+                Some(&("match", _)) => statement == 0 && line.len() > 2 && line[line.len() - 1].tk_type == TkType::Operator("OPERATOR{DOIS_PONTOS}"),
+                // dentro do bloco de um match so pode haver case, mesmo que incompleto
+                Some(&("case", _)) => statement == 0 && match_blocks.last() == Some(&depth),
+                Some(&("type", _)) => line.len() > 3 && line[1].tk_type == TkType::Identifier && matches!(
+                    line[2].tk_type,
+                    TkType::Operator("OPERATOR{IGUAL}") | TkType::Operator("OPERATOR{COLCHETES_ESQUERDO}")
+                ),
+                _ => false
+            };
+
+            if let (true, Some(&(lexema, id))) = (is_keyword, soft) {
+                tokens[first].tk_type = TkType::ReservedWord(id);
+
+                if lexema == "match" {
+                    match_blocks.push(depth + 1);
+                }
+            }
+
+            if statement == 0 {
+                starts = statement_starts(&tokens[start..end]);
+            }
+
+            statement += 1;
+        }
+
+        start = end + 1;
+    }
+}
+
+// como o tokenizer do python 3.5 e 3.6: async antes de def e reservado, e async e await passam a
+// ser reservados do async def ate o fim do corpo dele
+fn mark_async_keywords(tokens: &mut [Token]) {
+    let mut depth = 0;
+    // profundidade das linhas com async def cujo corpo ainda nao terminou
+    let mut async_defs: Vec<usize> = Vec::new();
+    let mut line_start = true;
+
+    for i in 0..tokens.len() {
+        match tokens[i].tk_type {
+            TkType::Indentaion => {
+                depth += 1;
+                continue;
+            },
+            TkType::Dedentation => {
+                depth -= 1;
+                continue;
+            },
+            TkType::EOS => {
+                line_start = true;
+                continue;
+            },
+            _ => {}
+        }
+
+        // uma linha de volta na profundidade do async def fecha o corpo dele
+        if line_start {
+            while async_defs.last().is_some_and(|&d| d >= depth) {
+                async_defs.pop();
+            }
+
+            line_start = false;
+        }
+
+        if tokens[i].tk_type != TkType::Identifier {
+            continue;
+        }
+
+        let async_def = tokens[i].lexema == "async" && tokens.get(i + 1).is_some_and(|t| t.tk_type == TkType::ReservedWord("RWORD{DEF}"));
+
+        if async_def {
+            async_defs.push(depth);
+        }
+
+        if async_def || (!async_defs.is_empty() && (tokens[i].lexema == "async" || tokens[i].lexema == "await")) {
+            let id = if tokens[i].lexema == "async" { "RWORD{ASYNC}" } else { "RWORD{AWAIT}" };
+            tokens[i].tk_type = TkType::ReservedWord(id);
+        }
+    }
+}
+
+fn opening_bracket(closer: char) -> char {
+    match closer {
+        ')' => '(',
+        ']' => '[',
+        _ => '{'
+    }
+}
+
+fn generate_tokens(source: &str, target: Version) -> Result<Vec<Token>, CompilationError> {
+    let mut tokens = Vec::new();
 
-    let mut buf = Vec::<u8>::new();
     let mut ind = Vec::new();
-    let mut scope = Vec::<char>::new();
+    // fechamento esperado e onde o bloco foi aberto
+    let mut scope = Vec::<(char, usize, usize)>::new();
 
     let mut row: usize = 0;
+    // fim da ultima linha, onde fica o END
+    let mut end = (0, 0);
 
-    while src.read_until(b'\n', &mut buf)? != 0 {
-        // TODO tratar os erros igual gente decente
-        let l = String::from_utf8(buf).expect("source file is not UTF-8");
-        
+    for l in source.split_inclusive('\n') {
         let mut line: Vec<char> = l.chars().collect();
 
         // FIXME por favor remover essa gambiarra
@@ -426,11 +671,13 @@ fn generate_tokens(src_file: &str) -> std::io::Result<Vec<Token>> {
             line.push('\n');
         }
 
+        end = (row, line.len() - 1);
+
         // Indentacao
         let line_indentation = get_line_indentation(&line);
 
         // Ignora se for uma linha em branco ou estiver dentro de um escopo
-        if line.len() >= line_indentation && line[line_indentation] != '\n' && scope.len() == 0 {
+        if line.len() >= line_indentation && line[line_indentation] != '\n' && line[line_indentation] != '#' && scope.is_empty() {
             // TODO rename
             let tot_ind = ind.iter().sum();
 
@@ -439,15 +686,10 @@ fn generate_tokens(src_file: &str) -> std::io::Result<Vec<Token>> {
 
                 while difference > 0 {
                     let last = match ind.last() {
-                        Some(&i) => i,
-                        None => panic!("Invalid indentation at: row {}, col {}", row, line_indentation),
+                        Some(&i) if difference >= i => i,
+                        _ => return Err(syntax_error(ErrorCode::InconsistentDedent, "unindent does not match any outer indentation level", row, line_indentation, 1))
                     };
-                    
-                    if difference < last {
-                        // TODO tratar os erros igual gente decente
-                        panic!("Invalid indentation at: row {}, col {}", row, line_indentation);
-                    }
-                
+
                     ind.pop();
                     difference -= last;
                     tokens.push(Token::new(TkType::Dedentation, "".to_owned(), row, 0));
@@ -460,7 +702,7 @@ fn generate_tokens(src_file: &str) -> std::io::Result<Vec<Token>> {
         }
 
         let mut col = line_indentation;
-        let mut eos = false;
+        let line_start = tokens.len();
 
         loop {
             match line[col] {
@@ -470,145 +712,127 @@ fn generate_tokens(src_file: &str) -> std::io::Result<Vec<Token>> {
                 '\t' => {
                     col += 1;
                 },
-                '\n' => {
-                    if scope.len() == 0 && eos {
+                '\n' | '#' => {
+                    if scope.is_empty() && tokens.len() > line_start {
                         tokens.push(Token::new(TkType::EOS, "".to_string(), row, col));
                     }
                     
                     break;
                 },
-                '#' => {
-                    break;
-                },
-                '(' => {
-                    let (_, optype) = OPERATORS[11];
-                    scope.push(')');
-                    tokens.push(Token::new(TkType::Operator(optype), "(".to_string(), row, col));
-                    col += 1;
-                },
-                ')' => {
-                    if let Some(&s) = scope.last() {
-                        if s != ')' {
-                            panic!("Expected '{}' but found ')' at: row {}, col {}", s, row, col)
-                        }
-                    } else {
-                        panic!("Unexpected ')' at: row {}, col {}", row, col)
-                    }
-                    
-                    let (_, optype) = OPERATORS[12];
-                    scope.pop();
-                    tokens.push(Token::new(TkType::Operator(optype), ")".to_string(), row, col));
-                    col += 1;
-                },
-                '[' => {
-                    let (_, optype) = OPERATORS[13];
-                    scope.push(']');
-                    tokens.push(Token::new(TkType::Operator(optype), "[".to_string(), row, col));
-                    col += 1;
-                },
-                ']' => {
-                    if let Some(&s) = scope.last() {
-                        if s != ']' {
-                            panic!("Expected '{}' but found ']' at: row {}, col {}", s, row, col)
-                        }
-                    } else {
-                        panic!("Unexpected ']' at: row {}, col {}", row, col)
+                '(' | '[' | '{' => {
+                    let (closer, index) = match line[col] {
+                        '(' => (')', 11),
+                        '[' => (']', 13),
+                        _ => ('}', 15)
+                    };
+
+                    // o mesmo limite do tokenizer do CPython
+                    if scope.len() >= 200 {
+                        return Err(syntax_error(ErrorCode::TooDeeplyNested, "too many nested parentheses", row, col, 1));
                     }
-                    
-                    let (_, optype) = OPERATORS[14];
-                    scope.pop();
-                    tokens.push(Token::new(TkType::Operator(optype), "]".to_string(), row, col));
-                    col += 1;
-                },
-                '{' => {
-                    let (_, optype) = OPERATORS[15];
-                    scope.push('}');
-                    tokens.push(Token::new(TkType::Operator(optype), "{".to_string(), row, col));
+
+                    let (_, optype) = OPERATORS[index];
+                    scope.push((closer, row, col));
+                    tokens.push(Token::new(TkType::Operator(optype), line[col].to_string(), row, col));
                     col += 1;
                 },
-                '}' => {
-                    if let Some(&s) = scope.last() {
-                        if s != '}' {
-                            panic!("Expected '{}' but found '}}' at: row {}, col {}", s, row, col)
+                ')' | ']' | '}' => {
+                    let index = match line[col] {
+                        ')' => 12,
+                        ']' => 14,
+                        _ => 16
+                    };
+
+                    match scope.last() {
+                        Some(&(closer, _, _)) if closer == line[col] => {},
+                        Some(&(closer, open_row, open_col)) => {
+                            let message = format!("closing parenthesis '{}' does not match opening parenthesis '{}'", line[col], opening_bracket(closer));
+                            let diagnostic = Diagnostic::new(ErrorCode::MismatchedBracket, &message, row, col, 1)
+                                .with_label(open_row, open_col, 1, "opening bracket here");
+
+                            return Err(CompilationError::SintaxError(Box::new(diagnostic)));
+                        },
+                        None => {
+                            let message = format!("unmatched '{}'", line[col]);
+                            return Err(syntax_error(ErrorCode::UnmatchedBracket, &message, row, col, 1));
                         }
-                    } else {
-                        panic!("Unexpected '}}' at: row {}, col {}", row, col)
                     }
-                    
-                    let (_, optype) = OPERATORS[12];
+
+                    let (_, optype) = OPERATORS[index];
                     scope.pop();
-                    tokens.push(Token::new(TkType::Operator(optype), "}".to_string(), row, col));
+                    tokens.push(Token::new(TkType::Operator(optype), line[col].to_string(), row, col));
                     col += 1;
                 },
-                '\'' => {
-                    // TODO bloco de comentario
-                    match get_string_literal(&line, '\'', col, row) {
-                        Some((token, icol)) => {
-                            tokens.push(token);
-                            col = icol;
-                        }
-                        // TODO errors
-                        None => panic!("Invalid Token at: row {}, col {}", row, col)
-                    }
-                },
-                '"' => {
+                '\'' | '"' => {
                     // TODO bloco de comentario
-                    match get_string_literal(&line, '"', col, row) {
-                        Some((token, icol)) => {
-                            tokens.push(token);
-                            col = icol;
-                        }
-                        // TODO errors
-                        None => panic!("Invalid Token at: row {}, col {}", row, col)
-                    }
+                    let (token, icol) = get_string_literal(&line, line[col], col, row)?;
+                    tokens.push(token);
+                    col = icol;
                 },
                 _ => {
-                    if let Some((token, icol)) = get_float_literal(&line, col, row) {
+                    if let Some((token, icol)) = get_float_literal(&line, col, row)? {
+                        tokens.push(token);
+                        col = icol;
+                        continue;
+                    }
+
+                    if let Some((token, icol)) = get_int_literal(&line, col, row)? {
                         tokens.push(token);
                         col = icol;
                         continue;
                     }
 
-                    if let Some((token, icol)) = get_int_literal(&line, col, row) {
+                    if let Some((token, icol)) = get_operator(&line, col, row)? {
                         tokens.push(token);
                         col = icol;
                         continue;
                     }
 
-                    if let Some((token, icol)) = get_operator(&line, col, row) {
+                    if let Some((token, icol)) = get_prefixed_string_literal(&line, col, row)? {
                         tokens.push(token);
                         col = icol;
                         continue;
                     }
 
-                    if let Some((token, icol)) = get_reserved_word_or_identifier(&line, col, row) {
+                    if let Some((token, icol)) = get_reserved_word_or_identifier(&line, col, row, target) {
                         tokens.push(token);
                         col = icol;
                         continue;
                     }
 
-                    // TODO errors
-                    panic!("Unidentified Token at: row {}, col {}", row, col)
+                    let c = line[col];
+                    let message = format!("invalid character '{}' (U+{:04X})", c, c as u32);
+                    return Err(syntax_error(ErrorCode::InvalidCharacter, &message, row, col, 1));
                 }
             }
-
-            eos = true;
         }
 
         row += 1;
-        
-        buf = l.into_bytes();
-        buf.clear();
     }
 
+    if let Some(&(closer, open_row, open_col)) = scope.first() {
+        let message = format!("'{}' was never closed", opening_bracket(closer));
+        return Err(syntax_error(ErrorCode::UnclosedBracket, &message, open_row, open_col, 1));
+    }
+
+    let (end_row, end_col) = end;
+
     for _ in ind.iter() {
-        tokens.push(Token::new(TkType::Dedentation, "".to_owned(), row, 0));
+        tokens.push(Token::new(TkType::Dedentation, "".to_owned(), end_row, 0));
+    }
+
+    tokens.push(Token::new(TkType::END, "".to_owned(), end_row, end_col));
+
+    mark_soft_keywords(&mut tokens);
+
+    if !target.is_python2() && target < Version(3, 7) {
+        mark_async_keywords(&mut tokens);
     }
     
     Ok(tokens)
 }
 
-fn dump_tokens(tokens: &Vec<Token>, filename: &str) -> std::io::Result<()> {
+fn dump_tokens(tokens: &[Token], filename: &str) -> std::io::Result<()> {
     let mut out = BufWriter::new(File::create(filename)?);
 
     for token in tokens.iter() {
@@ -619,32 +843,172 @@ fn dump_tokens(tokens: &Vec<Token>, filename: &str) -> std::io::Result<()> {
     Ok(())
 }
 
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 enum PossibleStates {
     STATEMENT_LIST,
     STATEMENT,
     STATEMENT_LIST_E,
     SCOPE,
-    ID_LIST,
+    PARAMETERS,
     EXPRESSION_LIST,
     EXPRESSION_STATEMENT,
     EXPRESSION,
     ELSE_STATEMENT,
-    ID_LIST_D,
+    PARAMETERS_D,
     EXPRESSION_STATEMENTL,
     ASSIGNMENT_EXPRESSIONL,
-    ID_OR_FCALL_D,
-    ID_OR_FCALL,
     EXPRESSION_LIST_d,
-    ASSIGNMENT_EXPRESSION,
-    EXPRESSION_A,
-    EXPRESSIONL,
-    EXPRESSION_B,
-    EXPRESSION_AL,
-    EXPRESSION_C,
-    EXPRESSION_BL,
-    EXPRESSION_D,
-    EXPRESSION_CL,
+    DISJUNCTION,
+    DISJUNCTIONL,
+    CONJUNCTION,
+    CONJUNCTIONL,
+    INVERSION,
+    COMPARISON,
+    COMPARISONL,
+    COMPARISON_OPERATOR,
+    IS_NOT_E,
+    BITWISE_OR,
+    BITWISE_ORL,
+    BITWISE_XOR,
+    BITWISE_XORL,
+    BITWISE_AND,
+    BITWISE_ANDL,
+    SHIFT_EXPRESSION,
+    SHIFT_EXPRESSIONL,
+    SUM,
+    SUML,
+    TERM,
+    TERML,
+    FACTOR,
+    POWER,
+    POWERL,
+    PRIMARY,
+    CONDITIONAL_E,
+    LAMBDA_EXPRESSION,
+    ATOM,
+    TRAILERS,
+    TRAILER,
+    CALL_ARGUMENTS,
+    SUBSCRIPT_LIST,
+    SUBSCRIPT_LIST_D,
+    SUBSCRIPT_LIST_E,
+    SUBSCRIPT,
+    SLICE,
+    SLICE_E,
+    SLICE_BOUND,
+    SLICE_STEP,
+    PARAMETER,
+    DEFAULT_E,
+    STAR_PARAMETER_E,
+    ARGUMENTS_D,
+    ARGUMENT,
+    ARGUMENTL,
+    PARENTHESIZED,
+    LIST_DISPLAY,
+    TESTLIST_COMP,
+    TESTLIST_COMPL,
+    TESTLIST_COMP_D,
+    TESTLIST_COMP_E,
+    STAR_OR_EXPRESSION,
+    DICT_OR_SET,
+    DICT_OR_SET_ITEMS,
+    DICT_OR_SETL,
+    DICT_TAIL,
+    DICT_ITEMS_D,
+    DICT_ITEMS_E,
+    DICT_ITEM,
+    COMP_FOR,
+    COMP_ITER,
+    COMP_IF,
+    TARGET_LIST,
+    TARGET_LIST_D,
+    TARGET_LIST_E,
+    TARGET,
+    DECORATORS,
+    DECORATORS_E,
+    DECORATOR,
+    DECORATED,
+    FUNCTION_DEF,
+    CLASS_DEF,
+    CLASS_ARGUMENTS_E,
+    RETURN_VALUE_E,
+    ID_LIST,
+    ID_LIST_D,
+    ASSERT_MESSAGE_E,
+    FOR_STATEMENT,
+    WITH_STATEMENT,
+    WITH_ITEMS_D,
+    WITH_ITEM,
+    WITH_TARGET_E,
+    ASYNC_STATEMENT,
+    ASSIGNMENT_VALUE,
+    YIELD_EXPRESSION,
+    YIELD_ARGUMENT_E,
+    AWAIT_PRIMARY,
+    LOOP_ELSE_E,
+    SIMPLE_STATEMENTS,
+    SIMPLE_STATEMENTS_D,
+    SIMPLE_STATEMENTS_E,
+    SIMPLE_STATEMENT,
+    STAR_EXPRESSIONS,
+    STAR_EXPRESSIONS_D,
+    STAR_EXPRESSIONS_E,
+    ASSIGNMENT_CHAIN,
+    ASSIGNMENT_CHAINL,
+    ANNOTATION_VALUE_E,
+    ANNOTATED_VALUE,
+    RETURNS_E,
+    ANNOTATION_E,
+    LAMBDA_PARAMETERS,
+    LAMBDA_PARAMETERS_D,
+    LAMBDA_PARAMETER,
+    LAMBDA_STAR_PARAMETER_E,
+    MATCH_STATEMENT,
+    CASE_BLOCKS,
+    CASE_BLOCKS_E,
+    CASE_BLOCK,
+    GUARD_E,
+    PATTERNS,
+    PATTERNS_D,
+    PATTERNS_E,
+    MAYBE_STAR_PATTERN,
+    PATTERN,
+    AS_PATTERN_E,
+    OR_PATTERN,
+    OR_PATTERNL,
+    CLOSED_PATTERN,
+    ATTRIBUTE_CHAIN,
+    CLASS_PATTERN_E,
+    LITERAL_PATTERN,
+    NUMBER,
+    COMPLEX_NUMBER_E,
+    PATTERN_GROUP,
+    MAPPING_PATTERN,
+    MAPPING_ITEMS_D,
+    MAPPING_ITEM,
+    CLASS_PATTERN_ARGUMENTS,
+    CLASS_PATTERN_ARGUMENTS_D,
+    CLASS_PATTERN_ARGUMENT,
+    KEYWORD_PATTERN_E,
+    SUBJECT,
+    SUBJECT_D,
+    SUBJECT_E,
+    NAMED_EXPRESSION,
+    NAMED_EXPRESSIONL,
+    STAR_NAMED_EXPRESSION,
+    PRINT_ARGUMENTS_E,
+    PRINT_ITEMS_D,
+    PRINT_ITEMS_E,
+    EXEC_NAMESPACE_E,
+    EXEC_LOCALS_E,
+    FILE,
+    TYPE_PARAMS_E,
+    TYPE_PARAM_LIST,
+    TYPE_PARAM_LIST_D,
+    TYPE_PARAM_LIST_E,
+    TYPE_PARAM,
+    TYPE_BOUND_E,
     Terminal(TkType),
     NOP
 }
@@ -655,857 +1019,1230 @@ struct HmIndex {
     token: TkType
 }
 
+fn op(lexema: &str) -> PossibleStates {
+    match OPERATORS.iter().find(|op| op.0 == lexema) {
+        Some(op) => PossibleStates::Terminal(TkType::Operator(op.1)),
+        None => panic!("Unknown operator in grammar: {}", lexema)
+    }
+}
+
+fn rword_or_op(lexema: &str) -> PossibleStates {
+    if lexema.chars().all(|c| c.is_ascii_alphabetic()) {
+        rword(lexema)
+    } else {
+        op(lexema)
+    }
+}
+
+fn rword(lexema: &str) -> PossibleStates {
+    match RESERVED_WORDS.iter().chain(SOFT_KEYWORDS.iter()).find(|w| w.0 == lexema) {
+        Some(w) => PossibleStates::Terminal(TkType::ReservedWord(w.1)),
+        None => panic!("Unknown reserved word in grammar: {}", lexema)
+    }
+}
+
+fn grammar() -> Vec<(PossibleStates, Vec<PossibleStates>)> {
+    use self::PossibleStates::*;
+
+    let id = Terminal(TkType::Identifier);
+    let eos = Terminal(TkType::EOS);
+    let indent = Terminal(TkType::Indentaion);
+    let dedent = Terminal(TkType::Dedentation);
+
+    vec![
+        // o arquivo pode ser vazio, so os blocos precisam de um statement
+        (FILE, vec![STATEMENT_LIST_E]),
+        (STATEMENT_LIST, vec![STATEMENT, STATEMENT_LIST_E]),
+        (STATEMENT_LIST_E, vec![STATEMENT, STATEMENT_LIST_E]),
+        (STATEMENT_LIST_E, vec![NOP]),
+        (SCOPE, vec![eos, indent, STATEMENT_LIST, dedent]),
+        (SCOPE, vec![SIMPLE_STATEMENTS]),
+
+        (STATEMENT, vec![FUNCTION_DEF]),
+        (STATEMENT, vec![CLASS_DEF]),
+        (STATEMENT, vec![DECORATORS, DECORATED]),
+        (STATEMENT, vec![SIMPLE_STATEMENTS]),
+        (STATEMENT, vec![FOR_STATEMENT]),
+        (STATEMENT, vec![WITH_STATEMENT]),
+        (STATEMENT, vec![MATCH_STATEMENT]),
+        (STATEMENT, vec![rword("async"), ASYNC_STATEMENT]),
+        (STATEMENT, vec![rword("while"), NAMED_EXPRESSION, op(":"), SCOPE, LOOP_ELSE_E]),
+        (STATEMENT, vec![rword("if"), NAMED_EXPRESSION, op(":"), SCOPE, ELSE_STATEMENT]),
+
+        (FOR_STATEMENT, vec![rword("for"), TARGET_LIST, rword("in"), STAR_EXPRESSIONS, op(":"), SCOPE, LOOP_ELSE_E]),
+        (LOOP_ELSE_E, vec![rword("else"), op(":"), SCOPE]),
+        (LOOP_ELSE_E, vec![NOP]),
+        (WITH_STATEMENT, vec![rword("with"), WITH_ITEM, WITH_ITEMS_D, op(":"), SCOPE]),
+        (WITH_ITEMS_D, vec![op(","), WITH_ITEM, WITH_ITEMS_D]),
+        (WITH_ITEMS_D, vec![NOP]),
+        (WITH_ITEM, vec![EXPRESSION, WITH_TARGET_E]),
+        (WITH_TARGET_E, vec![rword("as"), TARGET]),
+        (WITH_TARGET_E, vec![NOP]),
+        (ASYNC_STATEMENT, vec![FUNCTION_DEF]),
+        (ASYNC_STATEMENT, vec![FOR_STATEMENT]),
+        (ASYNC_STATEMENT, vec![WITH_STATEMENT]),
+
+        (SIMPLE_STATEMENTS, vec![SIMPLE_STATEMENT, SIMPLE_STATEMENTS_D, eos]),
+        (SIMPLE_STATEMENTS_D, vec![op(";"), SIMPLE_STATEMENTS_E]),
+        (SIMPLE_STATEMENTS_D, vec![NOP]),
+        (SIMPLE_STATEMENTS_E, vec![SIMPLE_STATEMENT, SIMPLE_STATEMENTS_D]),
+        (SIMPLE_STATEMENTS_E, vec![NOP]),
+        (SIMPLE_STATEMENT, vec![rword("break")]),
+        (SIMPLE_STATEMENT, vec![rword("continue")]),
+        (SIMPLE_STATEMENT, vec![rword("return"), RETURN_VALUE_E]),
+        (SIMPLE_STATEMENT, vec![rword("pass")]),
+        (SIMPLE_STATEMENT, vec![rword("del"), TARGET_LIST]),
+        (SIMPLE_STATEMENT, vec![rword("global"), ID_LIST]),
+        (SIMPLE_STATEMENT, vec![rword("nonlocal"), ID_LIST]),
+        (SIMPLE_STATEMENT, vec![rword("assert"), EXPRESSION, ASSERT_MESSAGE_E]),
+        (SIMPLE_STATEMENT, vec![EXPRESSION_STATEMENT]),
+        (SIMPLE_STATEMENT, vec![rword("type"), id, TYPE_PARAMS_E, op("="), EXPRESSION]),
+        // print e exec so sao palavras reservadas no python 2
+        (SIMPLE_STATEMENT, vec![rword("print"), PRINT_ARGUMENTS_E]),
+        (SIMPLE_STATEMENT, vec![rword("exec"), BITWISE_OR, EXEC_NAMESPACE_E]),
+        (PRINT_ARGUMENTS_E, vec![op(">>"), EXPRESSION, PRINT_ITEMS_D]),
+        (PRINT_ARGUMENTS_E, vec![EXPRESSION, PRINT_ITEMS_D]),
+        (PRINT_ARGUMENTS_E, vec![NOP]),
+        (PRINT_ITEMS_D, vec![op(","), PRINT_ITEMS_E]),
+        (PRINT_ITEMS_D, vec![NOP]),
+        (PRINT_ITEMS_E, vec![EXPRESSION, PRINT_ITEMS_D]),
+        (PRINT_ITEMS_E, vec![NOP]),
+        (EXEC_NAMESPACE_E, vec![rword("in"), EXPRESSION, EXEC_LOCALS_E]),
+        (EXEC_NAMESPACE_E, vec![NOP]),
+        (EXEC_LOCALS_E, vec![op(","), EXPRESSION]),
+        (EXEC_LOCALS_E, vec![NOP]),
+        (TYPE_PARAMS_E, vec![op("["), TYPE_PARAM_LIST, op("]")]),
+        (TYPE_PARAMS_E, vec![NOP]),
+        (TYPE_PARAM_LIST, vec![TYPE_PARAM, TYPE_PARAM_LIST_D]),
+        (TYPE_PARAM_LIST_D, vec![op(","), TYPE_PARAM_LIST_E]),
+        (TYPE_PARAM_LIST_D, vec![NOP]),
+        (TYPE_PARAM_LIST_E, vec![TYPE_PARAM, TYPE_PARAM_LIST_D]),
+        (TYPE_PARAM_LIST_E, vec![NOP]),
+        // T, T: limite, *Ts e **P
+        (TYPE_PARAM, vec![id, TYPE_BOUND_E]),
+        (TYPE_PARAM, vec![op("*"), id]),
+        (TYPE_PARAM, vec![op("**"), id]),
+        (TYPE_BOUND_E, vec![op(":"), EXPRESSION]),
+        (TYPE_BOUND_E, vec![NOP]),
+
+        (MATCH_STATEMENT, vec![rword("match"), SUBJECT, op(":"), eos, indent, CASE_BLOCKS, dedent]),
+        (SUBJECT, vec![STAR_NAMED_EXPRESSION, SUBJECT_D]),
+        (SUBJECT_D, vec![op(","), SUBJECT_E]),
+        (SUBJECT_D, vec![NOP]),
+        (SUBJECT_E, vec![STAR_NAMED_EXPRESSION, SUBJECT_D]),
+        (SUBJECT_E, vec![NOP]),
+        (CASE_BLOCKS, vec![CASE_BLOCK, CASE_BLOCKS_E]),
+        (CASE_BLOCKS_E, vec![CASE_BLOCK, CASE_BLOCKS_E]),
+        (CASE_BLOCKS_E, vec![NOP]),
+        (CASE_BLOCK, vec![rword("case"), PATTERNS, GUARD_E, op(":"), SCOPE]),
+        (GUARD_E, vec![rword("if"), NAMED_EXPRESSION]),
+        (GUARD_E, vec![NOP]),
+
+        (PATTERNS, vec![MAYBE_STAR_PATTERN, PATTERNS_D]),
+        (PATTERNS_D, vec![op(","), PATTERNS_E]),
+        (PATTERNS_D, vec![NOP]),
+        (PATTERNS_E, vec![MAYBE_STAR_PATTERN, PATTERNS_D]),
+        (PATTERNS_E, vec![NOP]),
+        (MAYBE_STAR_PATTERN, vec![op("*"), id]),
+        (MAYBE_STAR_PATTERN, vec![PATTERN]),
+        (PATTERN, vec![OR_PATTERN, AS_PATTERN_E]),
+        (AS_PATTERN_E, vec![rword("as"), id]),
+        (AS_PATTERN_E, vec![NOP]),
+        (OR_PATTERN, vec![CLOSED_PATTERN, OR_PATTERNL]),
+        (OR_PATTERNL, vec![op("|"), OR_PATTERN]),
+        (OR_PATTERNL, vec![NOP]),
+        // captura, valor (a.b) ou classe (a.B(...))
+        (CLOSED_PATTERN, vec![id, ATTRIBUTE_CHAIN, CLASS_PATTERN_E]),
+        (CLOSED_PATTERN, vec![LITERAL_PATTERN]),
+        (CLOSED_PATTERN, vec![op("("), PATTERN_GROUP, op(")")]),
+        (CLOSED_PATTERN, vec![op("["), PATTERN_GROUP, op("]")]),
+        (CLOSED_PATTERN, vec![op("{"), MAPPING_PATTERN, op("}")]),
+        (ATTRIBUTE_CHAIN, vec![op("."), id, ATTRIBUTE_CHAIN]),
+        (ATTRIBUTE_CHAIN, vec![NOP]),
+        (CLASS_PATTERN_E, vec![op("("), CLASS_PATTERN_ARGUMENTS, op(")")]),
+        (CLASS_PATTERN_E, vec![NOP]),
+        (LITERAL_PATTERN, vec![op("-"), NUMBER, COMPLEX_NUMBER_E]),
+        (LITERAL_PATTERN, vec![NUMBER, COMPLEX_NUMBER_E]),
+        (LITERAL_PATTERN, vec![Terminal(TkType::Literal(LiteralTypes::String))]),
+        (LITERAL_PATTERN, vec![rword("None")]),
+        (LITERAL_PATTERN, vec![rword("True")]),
+        (LITERAL_PATTERN, vec![rword("False")]),
+        (NUMBER, vec![Terminal(TkType::Literal(LiteralTypes::Int))]),
+        (NUMBER, vec![Terminal(TkType::Literal(LiteralTypes::Float))]),
+        (NUMBER, vec![Terminal(TkType::Literal(LiteralTypes::Imaginary))]),
+        // 1 + 2j, so a parte imaginaria pode vir depois do sinal
+        (COMPLEX_NUMBER_E, vec![op("+"), NUMBER]),
+        (COMPLEX_NUMBER_E, vec![op("-"), NUMBER]),
+        (COMPLEX_NUMBER_E, vec![NOP]),
+        (PATTERN_GROUP, vec![PATTERNS]),
+        (PATTERN_GROUP, vec![NOP]),
+        (MAPPING_PATTERN, vec![MAPPING_ITEM, MAPPING_ITEMS_D]),
+        (MAPPING_PATTERN, vec![NOP]),
+        (MAPPING_ITEMS_D, vec![op(","), MAPPING_PATTERN]),
+        (MAPPING_ITEMS_D, vec![NOP]),
+        (MAPPING_ITEM, vec![CLOSED_PATTERN, op(":"), PATTERN]),
+        (MAPPING_ITEM, vec![op("**"), id]),
+        (CLASS_PATTERN_ARGUMENTS, vec![CLASS_PATTERN_ARGUMENT, CLASS_PATTERN_ARGUMENTS_D]),
+        (CLASS_PATTERN_ARGUMENTS, vec![NOP]),
+        (CLASS_PATTERN_ARGUMENTS_D, vec![op(","), CLASS_PATTERN_ARGUMENTS]),
+        (CLASS_PATTERN_ARGUMENTS_D, vec![NOP]),
+        // nome=padrao e validado na conversao, como nos argumentos de chamada
+        (CLASS_PATTERN_ARGUMENT, vec![PATTERN, KEYWORD_PATTERN_E]),
+        (KEYWORD_PATTERN_E, vec![op("="), PATTERN]),
+        (KEYWORD_PATTERN_E, vec![NOP]),
+
+        (RETURN_VALUE_E, vec![EXPRESSION_LIST]),
+        (RETURN_VALUE_E, vec![NOP]),
+        (ID_LIST, vec![id, ID_LIST_D]),
+        (ID_LIST_D, vec![op(","), id, ID_LIST_D]),
+        (ID_LIST_D, vec![NOP]),
+        (ASSERT_MESSAGE_E, vec![op(","), EXPRESSION]),
+        (ASSERT_MESSAGE_E, vec![NOP]),
+
+        (DECORATORS, vec![DECORATOR, DECORATORS_E]),
+        (DECORATORS_E, vec![DECORATOR, DECORATORS_E]),
+        (DECORATORS_E, vec![NOP]),
+        (DECORATOR, vec![op("@"), NAMED_EXPRESSION, eos]),
+        (DECORATED, vec![FUNCTION_DEF]),
+        (DECORATED, vec![CLASS_DEF]),
+        (DECORATED, vec![rword("async"), FUNCTION_DEF]),
+        (FUNCTION_DEF, vec![rword("def"), id, op("("), PARAMETERS, op(")"), RETURNS_E, op(":"), SCOPE]),
+        (RETURNS_E, vec![op("->"), EXPRESSION]),
+        (RETURNS_E, vec![NOP]),
+        (CLASS_DEF, vec![rword("class"), id, CLASS_ARGUMENTS_E, op(":"), SCOPE]),
+        (CLASS_ARGUMENTS_E, vec![op("("), CALL_ARGUMENTS, op(")")]),
+        (CLASS_ARGUMENTS_E, vec![NOP]),
+
+        (PARAMETERS, vec![PARAMETER, PARAMETERS_D]),
+        (PARAMETERS, vec![NOP]),
+        (PARAMETERS_D, vec![op(","), PARAMETERS]),
+        (PARAMETERS_D, vec![NOP]),
+        (PARAMETER, vec![id, ANNOTATION_E, DEFAULT_E]),
+        (PARAMETER, vec![op("*"), STAR_PARAMETER_E]),
+        (PARAMETER, vec![op("**"), id, ANNOTATION_E]),
+        (PARAMETER, vec![op("/")]),
+        (ANNOTATION_E, vec![op(":"), EXPRESSION]),
+        (ANNOTATION_E, vec![NOP]),
+        (DEFAULT_E, vec![op("="), EXPRESSION]),
+        (DEFAULT_E, vec![NOP]),
+        (STAR_PARAMETER_E, vec![id, ANNOTATION_E]),
+        (STAR_PARAMETER_E, vec![NOP]),
+        // lambda nao tem anotacoes, o ':' encerra os parametros
+        (LAMBDA_PARAMETERS, vec![LAMBDA_PARAMETER, LAMBDA_PARAMETERS_D]),
+        (LAMBDA_PARAMETERS, vec![NOP]),
+        (LAMBDA_PARAMETERS_D, vec![op(","), LAMBDA_PARAMETERS]),
+        (LAMBDA_PARAMETERS_D, vec![NOP]),
+        (LAMBDA_PARAMETER, vec![id, DEFAULT_E]),
+        (LAMBDA_PARAMETER, vec![op("*"), LAMBDA_STAR_PARAMETER_E]),
+        (LAMBDA_PARAMETER, vec![op("**"), id]),
+        (LAMBDA_PARAMETER, vec![op("/")]),
+        (LAMBDA_STAR_PARAMETER_E, vec![id]),
+        (LAMBDA_STAR_PARAMETER_E, vec![NOP]),
+
+        (EXPRESSION_STATEMENT, vec![STAR_EXPRESSIONS, EXPRESSION_STATEMENTL]),
+        (EXPRESSION_STATEMENT, vec![YIELD_EXPRESSION]),
+        (EXPRESSION_STATEMENTL, vec![ASSIGNMENT_EXPRESSIONL]),
+        (EXPRESSION_STATEMENTL, vec![NOP]),
+        (STAR_EXPRESSIONS, vec![STAR_OR_EXPRESSION, STAR_EXPRESSIONS_D]),
+        (STAR_EXPRESSIONS_D, vec![op(","), STAR_EXPRESSIONS_E]),
+        (STAR_EXPRESSIONS_D, vec![NOP]),
+        (STAR_EXPRESSIONS_E, vec![STAR_OR_EXPRESSION, STAR_EXPRESSIONS_D]),
+        (STAR_EXPRESSIONS_E, vec![NOP]),
+        // a = b = c: todos menos o ultimo sao alvos
+        (ASSIGNMENT_EXPRESSIONL, vec![op("="), ASSIGNMENT_CHAIN]),
+        (ASSIGNMENT_CHAIN, vec![STAR_EXPRESSIONS, ASSIGNMENT_CHAINL]),
+        (ASSIGNMENT_CHAIN, vec![YIELD_EXPRESSION]),
+        (ASSIGNMENT_CHAINL, vec![op("="), ASSIGNMENT_CHAIN]),
+        (ASSIGNMENT_CHAINL, vec![NOP]),
+        (ASSIGNMENT_EXPRESSIONL, vec![op("+="), ASSIGNMENT_VALUE]),
+        (ASSIGNMENT_EXPRESSIONL, vec![op("-="), ASSIGNMENT_VALUE]),
+        (ASSIGNMENT_EXPRESSIONL, vec![op("*="), ASSIGNMENT_VALUE]),
+        (ASSIGNMENT_EXPRESSIONL, vec![op("/="), ASSIGNMENT_VALUE]),
+        (ASSIGNMENT_EXPRESSIONL, vec![op("//="), ASSIGNMENT_VALUE]),
+        (ASSIGNMENT_EXPRESSIONL, vec![op("%="), ASSIGNMENT_VALUE]),
+        (ASSIGNMENT_EXPRESSIONL, vec![op("@="), ASSIGNMENT_VALUE]),
+        (ASSIGNMENT_EXPRESSIONL, vec![op("&="), ASSIGNMENT_VALUE]),
+        (ASSIGNMENT_EXPRESSIONL, vec![op("|="), ASSIGNMENT_VALUE]),
+        (ASSIGNMENT_EXPRESSIONL, vec![op("^="), ASSIGNMENT_VALUE]),
+        (ASSIGNMENT_EXPRESSIONL, vec![op(">>="), ASSIGNMENT_VALUE]),
+        (ASSIGNMENT_EXPRESSIONL, vec![op("<<="), ASSIGNMENT_VALUE]),
+        (ASSIGNMENT_EXPRESSIONL, vec![op("**="), ASSIGNMENT_VALUE]),
+        // x: int = 0
+        (ASSIGNMENT_EXPRESSIONL, vec![op(":"), EXPRESSION, ANNOTATION_VALUE_E]),
+        (ANNOTATION_VALUE_E, vec![op("="), ANNOTATED_VALUE]),
+        (ANNOTATION_VALUE_E, vec![NOP]),
+        (ANNOTATED_VALUE, vec![STAR_EXPRESSIONS]),
+        (ANNOTATED_VALUE, vec![YIELD_EXPRESSION]),
+        (ASSIGNMENT_VALUE, vec![EXPRESSION_LIST]),
+        (ASSIGNMENT_VALUE, vec![YIELD_EXPRESSION]),
+        (YIELD_EXPRESSION, vec![rword("yield"), YIELD_ARGUMENT_E]),
+        (YIELD_ARGUMENT_E, vec![rword("from"), EXPRESSION]),
+        (YIELD_ARGUMENT_E, vec![EXPRESSION_LIST]),
+        (YIELD_ARGUMENT_E, vec![NOP]),
+
+        (EXPRESSION_LIST, vec![EXPRESSION, EXPRESSION_LIST_d]),
+        (EXPRESSION_LIST_d, vec![op(","), EXPRESSION, EXPRESSION_LIST_d]),
+        (EXPRESSION_LIST_d, vec![NOP]),
+
+        // x := valor so vale onde o CPython aceita named_expression
+        (NAMED_EXPRESSION, vec![EXPRESSION, NAMED_EXPRESSIONL]),
+        (NAMED_EXPRESSIONL, vec![op(":="), EXPRESSION]),
+        (NAMED_EXPRESSIONL, vec![NOP]),
+        (EXPRESSION, vec![DISJUNCTION, CONDITIONAL_E]),
+        (EXPRESSION, vec![LAMBDA_EXPRESSION]),
+        (CONDITIONAL_E, vec![rword("if"), DISJUNCTION, rword("else"), EXPRESSION]),
+        (CONDITIONAL_E, vec![NOP]),
+        (LAMBDA_EXPRESSION, vec![rword("lambda"), LAMBDA_PARAMETERS, op(":"), EXPRESSION]),
+        (DISJUNCTION, vec![CONJUNCTION, DISJUNCTIONL]),
+        (DISJUNCTIONL, vec![rword("or"), DISJUNCTION]),
+        (DISJUNCTIONL, vec![NOP]),
+        (CONJUNCTION, vec![INVERSION, CONJUNCTIONL]),
+        (CONJUNCTIONL, vec![rword("and"), CONJUNCTION]),
+        (CONJUNCTIONL, vec![NOP]),
+        (INVERSION, vec![rword("not"), INVERSION]),
+        (INVERSION, vec![COMPARISON]),
+        (COMPARISON, vec![BITWISE_OR, COMPARISONL]),
+        (COMPARISONL, vec![COMPARISON_OPERATOR, COMPARISON]),
+        (COMPARISONL, vec![NOP]),
+        (COMPARISON_OPERATOR, vec![op("==")]),
+        (COMPARISON_OPERATOR, vec![op("!=")]),
+        (COMPARISON_OPERATOR, vec![op("<")]),
+        (COMPARISON_OPERATOR, vec![op("<=")]),
+        (COMPARISON_OPERATOR, vec![op(">")]),
+        (COMPARISON_OPERATOR, vec![op(">=")]),
+        (COMPARISON_OPERATOR, vec![rword("in")]),
+        (COMPARISON_OPERATOR, vec![rword("not"), rword("in")]),
+        (COMPARISON_OPERATOR, vec![rword("is"), IS_NOT_E]),
+        (IS_NOT_E, vec![rword("not")]),
+        (IS_NOT_E, vec![NOP]),
+        (BITWISE_OR, vec![BITWISE_XOR, BITWISE_ORL]),
+        (BITWISE_ORL, vec![op("|"), BITWISE_OR]),
+        (BITWISE_ORL, vec![NOP]),
+        (BITWISE_XOR, vec![BITWISE_AND, BITWISE_XORL]),
+        (BITWISE_XORL, vec![op("^"), BITWISE_XOR]),
+        (BITWISE_XORL, vec![NOP]),
+        (BITWISE_AND, vec![SHIFT_EXPRESSION, BITWISE_ANDL]),
+        (BITWISE_ANDL, vec![op("&"), BITWISE_AND]),
+        (BITWISE_ANDL, vec![NOP]),
+        (SHIFT_EXPRESSION, vec![SUM, SHIFT_EXPRESSIONL]),
+        (SHIFT_EXPRESSIONL, vec![op("<<"), SHIFT_EXPRESSION]),
+        (SHIFT_EXPRESSIONL, vec![op(">>"), SHIFT_EXPRESSION]),
+        (SHIFT_EXPRESSIONL, vec![NOP]),
+        (SUM, vec![TERM, SUML]),
+        (SUML, vec![op("+"), SUM]),
+        (SUML, vec![op("-"), SUM]),
+        (SUML, vec![NOP]),
+        (TERM, vec![FACTOR, TERML]),
+        (TERML, vec![op("*"), TERM]),
+        (TERML, vec![op("/"), TERM]),
+        (TERML, vec![op("//"), TERM]),
+        (TERML, vec![op("%"), TERM]),
+        (TERML, vec![op("@"), TERM]),
+        (TERML, vec![NOP]),
+        (FACTOR, vec![op("+"), FACTOR]),
+        (FACTOR, vec![op("-"), FACTOR]),
+        (FACTOR, vec![op("~"), FACTOR]),
+        (FACTOR, vec![POWER]),
+        // ** e associativo a direita e o expoente pode ter sinal: 2 ** -1
+        (POWER, vec![PRIMARY, POWERL]),
+        (POWERL, vec![op("**"), FACTOR]),
+        (POWERL, vec![NOP]),
+        (PRIMARY, vec![ATOM, TRAILERS]),
+        (PRIMARY, vec![AWAIT_PRIMARY]),
+        (AWAIT_PRIMARY, vec![rword("await"), ATOM, TRAILERS]),
+
+        (ATOM, vec![op("("), PARENTHESIZED, op(")")]),
+        (ATOM, vec![op("["), LIST_DISPLAY, op("]")]),
+        (ATOM, vec![op("{"), DICT_OR_SET, op("}")]),
+        (ATOM, vec![id]),
+        (ATOM, vec![Terminal(TkType::Literal(LiteralTypes::Int))]),
+        (ATOM, vec![Terminal(TkType::Literal(LiteralTypes::Float))]),
+        (ATOM, vec![Terminal(TkType::Literal(LiteralTypes::Imaginary))]),
+        (ATOM, vec![Terminal(TkType::Literal(LiteralTypes::String))]),
+        (ATOM, vec![rword("True")]),
+        (ATOM, vec![rword("False")]),
+        (ATOM, vec![rword("None")]),
+        (ATOM, vec![op("...")]),
+
+        (PARENTHESIZED, vec![TESTLIST_COMP]),
+        (PARENTHESIZED, vec![YIELD_EXPRESSION]),
+        (PARENTHESIZED, vec![NOP]),
+        (LIST_DISPLAY, vec![TESTLIST_COMP]),
+        (LIST_DISPLAY, vec![NOP]),
+        (TESTLIST_COMP, vec![STAR_NAMED_EXPRESSION, TESTLIST_COMPL]),
+        (TESTLIST_COMPL, vec![COMP_FOR]),
+        (TESTLIST_COMPL, vec![TESTLIST_COMP_D]),
+        (TESTLIST_COMP_D, vec![op(","), TESTLIST_COMP_E]),
+        (TESTLIST_COMP_D, vec![NOP]),
+        (TESTLIST_COMP_E, vec![STAR_NAMED_EXPRESSION, TESTLIST_COMP_D]),
+        (TESTLIST_COMP_E, vec![NOP]),
+        (STAR_OR_EXPRESSION, vec![EXPRESSION]),
+        (STAR_OR_EXPRESSION, vec![op("*"), BITWISE_OR]),
+        (STAR_NAMED_EXPRESSION, vec![NAMED_EXPRESSION]),
+        (STAR_NAMED_EXPRESSION, vec![op("*"), BITWISE_OR]),
+
+        (DICT_OR_SET, vec![DICT_OR_SET_ITEMS]),
+        (DICT_OR_SET, vec![NOP]),
+        (DICT_OR_SET_ITEMS, vec![EXPRESSION, DICT_OR_SETL]),
+        (DICT_OR_SET_ITEMS, vec![op("**"), BITWISE_OR, DICT_TAIL]),
+        (DICT_OR_SET_ITEMS, vec![op("*"), BITWISE_OR, TESTLIST_COMPL]),
+        (DICT_OR_SETL, vec![op(":"), EXPRESSION, DICT_TAIL]),
+        (DICT_OR_SETL, vec![TESTLIST_COMPL]),
+        (DICT_OR_SETL, vec![op(":="), EXPRESSION, TESTLIST_COMPL]),
+        (DICT_TAIL, vec![COMP_FOR]),
+        (DICT_TAIL, vec![DICT_ITEMS_D]),
+        (DICT_ITEMS_D, vec![op(","), DICT_ITEMS_E]),
+        (DICT_ITEMS_D, vec![NOP]),
+        (DICT_ITEMS_E, vec![DICT_ITEM, DICT_ITEMS_D]),
+        (DICT_ITEMS_E, vec![NOP]),
+        (DICT_ITEM, vec![EXPRESSION, op(":"), EXPRESSION]),
+        (DICT_ITEM, vec![op("**"), BITWISE_OR]),
+
+        (COMP_FOR, vec![rword("for"), TARGET_LIST, rword("in"), DISJUNCTION, COMP_ITER]),
+        (COMP_FOR, vec![rword("async"), rword("for"), TARGET_LIST, rword("in"), DISJUNCTION, COMP_ITER]),
+        (COMP_ITER, vec![COMP_FOR]),
+        (COMP_ITER, vec![COMP_IF]),
+        (COMP_ITER, vec![NOP]),
+        (COMP_IF, vec![rword("if"), DISJUNCTION, COMP_ITER]),
+        (TARGET_LIST, vec![TARGET, TARGET_LIST_D]),
+        (TARGET_LIST_D, vec![op(","), TARGET_LIST_E]),
+        (TARGET_LIST_D, vec![NOP]),
+        (TARGET_LIST_E, vec![TARGET, TARGET_LIST_D]),
+        (TARGET_LIST_E, vec![NOP]),
+        (TARGET, vec![BITWISE_OR]),
+        (TARGET, vec![op("*"), BITWISE_OR]),
+
+        (TRAILERS, vec![TRAILER, TRAILERS]),
+        (TRAILERS, vec![NOP]),
+        (TRAILER, vec![op("("), CALL_ARGUMENTS, op(")")]),
+        (TRAILER, vec![op("["), SUBSCRIPT_LIST, op("]")]),
+        (TRAILER, vec![op("."), id]),
+        (CALL_ARGUMENTS, vec![ARGUMENT, ARGUMENTS_D]),
+        (CALL_ARGUMENTS, vec![NOP]),
+        (ARGUMENTS_D, vec![op(","), CALL_ARGUMENTS]),
+        (ARGUMENTS_D, vec![NOP]),
+        (ARGUMENT, vec![NAMED_EXPRESSION, ARGUMENTL]),
+        (ARGUMENT, vec![op("*"), EXPRESSION]),
+        (ARGUMENT, vec![op("**"), EXPRESSION]),
+        (ARGUMENTL, vec![op("="), EXPRESSION]),
+        (ARGUMENTL, vec![COMP_FOR]),
+        (ARGUMENTL, vec![NOP]),
+
+        (SUBSCRIPT_LIST, vec![SUBSCRIPT, SUBSCRIPT_LIST_D]),
+        (SUBSCRIPT_LIST_D, vec![op(","), SUBSCRIPT_LIST_E]),
+        (SUBSCRIPT_LIST_D, vec![NOP]),
+        (SUBSCRIPT_LIST_E, vec![SUBSCRIPT, SUBSCRIPT_LIST_D]),
+        (SUBSCRIPT_LIST_E, vec![NOP]),
+        (SUBSCRIPT, vec![NAMED_EXPRESSION, SLICE_E]),
+        (SUBSCRIPT, vec![SLICE]),
+        (SLICE_E, vec![SLICE]),
+        (SLICE_E, vec![NOP]),
+        (SLICE, vec![op(":"), SLICE_BOUND, SLICE_STEP]),
+        (SLICE_BOUND, vec![EXPRESSION]),
+        (SLICE_BOUND, vec![NOP]),
+        (SLICE_STEP, vec![op(":"), SLICE_BOUND]),
+        (SLICE_STEP, vec![NOP]),
+
+        (ELSE_STATEMENT, vec![rword("else"), op(":"), SCOPE]),
+        (ELSE_STATEMENT, vec![rword("elif"), NAMED_EXPRESSION, op(":"), SCOPE, ELSE_STATEMENT]),
+        (ELSE_STATEMENT, vec![NOP]),
+    ]
+}
+
+fn sequence_first(
+    sequence: &[PossibleStates],
+    first: &HashMap<PossibleStates, HashSet<TkType>>,
+    nullable: &HashSet<PossibleStates>
+) -> (HashSet<TkType>, bool) {
+    let mut set = HashSet::new();
+
+    for s in sequence.iter() {
+        match *s {
+            PossibleStates::NOP => continue,
+            PossibleStates::Terminal(tk_type) => {
+                set.insert(tk_type);
+                return (set, false);
+            },
+            state => {
+                if let Some(f) = first.get(&state) {
+                    set.extend(f.iter().cloned());
+                }
+
+                if !nullable.contains(&state) {
+                    return (set, false);
+                }
+            }
+        }
+    }
+
+    (set, true)
+}
+
+// FIRST de cada estado e os estados anulaveis
+type FirstSets = (HashMap<PossibleStates, HashSet<TkType>>, HashSet<PossibleStates>);
+
+// FIRST e anulaveis
+fn first_sets(productions: &[(PossibleStates, Vec<PossibleStates>)]) -> FirstSets {
+    let mut first: HashMap<PossibleStates, HashSet<TkType>> = HashMap::new();
+    let mut nullable = HashSet::new();
+    let mut changed = true;
+
+    while changed {
+        changed = false;
+
+        for (state, prod) in productions.iter() {
+            let (set, is_nullable) = sequence_first(prod, &first, &nullable);
+            let entry = first.entry(*state).or_default();
+            let before = entry.len();
+            entry.extend(set);
+
+            if entry.len() != before {
+                changed = true;
+            }
+
+            if is_nullable && nullable.insert(*state) {
+                changed = true;
+            }
+        }
+    }
+
+    (first, nullable)
+}
+
+// a gramatica nao muda durante a execucao, mas o parse roda de novo para cada correcao testada:
+// a tabela e os conjuntos FIRST sao montados uma vez so
+fn lookup_table() -> &'static HashMap<HmIndex, Vec<PossibleStates>> {
+    static TABLE: OnceLock<HashMap<HmIndex, Vec<PossibleStates>>> = OnceLock::new();
+    TABLE.get_or_init(generate_lookup_table)
+}
+
+fn grammar_first_sets() -> &'static FirstSets {
+    static FIRST: OnceLock<FirstSets> = OnceLock::new();
+    FIRST.get_or_init(|| first_sets(&grammar()))
+}
+
 fn generate_lookup_table() -> HashMap<HmIndex, Vec<PossibleStates>> {
+    let productions = grammar();
+
+    for (_, prod) in productions.iter() {
+        for s in prod.iter() {
+            if let PossibleStates::Terminal(_) = s {
+                continue;
+            }
+
+            if *s != PossibleStates::NOP && !productions.iter().any(|(state, _)| state == s) {
+                panic!("State {:?} has no productions", s);
+            }
+        }
+    }
+
+    let (first, nullable) = first_sets(&productions);
+
+    // FOLLOW
+    let mut follow: HashMap<PossibleStates, HashSet<TkType>> = HashMap::new();
+    follow.entry(PossibleStates::FILE).or_default().insert(TkType::END);
+    let mut changed = true;
+
+    while changed {
+        changed = false;
+
+        for (state, prod) in productions.iter() {
+            for (i, s) in prod.iter().enumerate() {
+                if let PossibleStates::Terminal(_) = s {
+                    continue;
+                }
+
+                if *s == PossibleStates::NOP {
+                    continue;
+                }
+
+                let (mut set, rest_nullable) = sequence_first(&prod[i + 1..], &first, &nullable);
+
+                if rest_nullable {
+                    if let Some(f) = follow.get(state) {
+                        set.extend(f.iter().cloned());
+                    }
+                }
+
+                let entry = follow.entry(*s).or_default();
+                let before = entry.len();
+                entry.extend(set);
+
+                if entry.len() != before {
+                    changed = true;
+                }
+            }
+        }
+    }
+
     let mut hm = HashMap::new();
 
-    let prod1 = vec![PossibleStates::STATEMENT, PossibleStates::STATEMENT_LIST_E];
-    let prod2 = vec![PossibleStates::STATEMENT, PossibleStates::STATEMENT_LIST_E];
-    let prod3 = vec![PossibleStates::NOP];
-    let prod4 = vec![PossibleStates::Terminal(TkType::Indentaion), PossibleStates::STATEMENT_LIST, PossibleStates::Terminal(TkType::Dedentation)];
-    let prod5 = vec![PossibleStates::Terminal(TkType::ReservedWord("RWORD{DEF}")), PossibleStates::Terminal(TkType::Identifier), PossibleStates::Terminal(TkType::Operator("OPERATOR{PARENTESES_ESQUERDO}")), PossibleStates::ID_LIST, PossibleStates::Terminal(TkType::Operator("OPERATOR{PARENTESES_DIREITO}")), PossibleStates::Terminal(TkType::Operator("OPERATOR{DOIS_PONTOS}")), PossibleStates::Terminal(TkType::EOS), PossibleStates::SCOPE];
-    let prod6 = vec![PossibleStates::Terminal(TkType::ReservedWord("RWORD{BREAK}")), PossibleStates::Terminal(TkType::EOS)];
-    let prod7 = vec![PossibleStates::Terminal(TkType::ReservedWord("RWORD{CONTINUE}")), PossibleStates::Terminal(TkType::EOS)];
-    let prod8 = vec![PossibleStates::Terminal(TkType::ReservedWord("RWORD{RETURN}")), PossibleStates::EXPRESSION_LIST, PossibleStates::Terminal(TkType::EOS)];
-    let prod9 = vec![PossibleStates::EXPRESSION_STATEMENT, PossibleStates::Terminal(TkType::EOS)];
-    let prod10 = vec![PossibleStates::Terminal(TkType::ReservedWord("RWORD{FOR}")), PossibleStates::Terminal(TkType::Identifier), PossibleStates::Terminal(TkType::ReservedWord("RWORD{IN}")), PossibleStates::Terminal(TkType::Identifier), PossibleStates::Terminal(TkType::Operator("OPERATOR{DOIS_PONTOS}")), PossibleStates::Terminal(TkType::EOS), PossibleStates::SCOPE];
-    let prod11 = vec![PossibleStates::Terminal(TkType::ReservedWord("RWORD{WHILE}")), PossibleStates::EXPRESSION, PossibleStates::Terminal(TkType::Operator("OPERATOR{DOIS_PONTOS}")), PossibleStates::Terminal(TkType::EOS), PossibleStates::SCOPE];
-    let prod12 = vec![PossibleStates::Terminal(TkType::ReservedWord("RWORD{IF}")), PossibleStates::EXPRESSION, PossibleStates::Terminal(TkType::Operator("OPERATOR{DOIS_PONTOS}")), PossibleStates::Terminal(TkType::EOS), PossibleStates::SCOPE, PossibleStates::ELSE_STATEMENT];
-    let prod13 = vec![PossibleStates::Terminal(TkType::Identifier), PossibleStates::ID_LIST_D];
-    let prod14 = vec![PossibleStates::Terminal(TkType::Operator("OPERATOR{VIRGULA}")), PossibleStates::Terminal(TkType::Identifier), PossibleStates::ID_LIST_D];
-    let prod15 = vec![PossibleStates::NOP];
-    let prod16 = vec![PossibleStates::Terminal(TkType::Identifier), PossibleStates::EXPRESSION_STATEMENTL];
-    let prod17 = vec![PossibleStates::Terminal(TkType::Operator("OPERATOR{PARENTESES_ESQUERDO}")), PossibleStates::EXPRESSION_LIST, PossibleStates::Terminal(TkType::Operator("OPERATOR{PARENTESES_DIREITO}"))];
-    let prod18 = vec![PossibleStates::ASSIGNMENT_EXPRESSIONL];
-    let prod19 = vec![PossibleStates::Terminal(TkType::Identifier), PossibleStates::ID_OR_FCALL_D];
-    let prod20 = vec![PossibleStates::Terminal(TkType::Operator("OPERATOR{PARENTESES_ESQUERDO}")), PossibleStates::EXPRESSION_LIST, PossibleStates::Terminal(TkType::Operator("OPERATOR{PARENTESES_DIREITO}"))];
-    let prod21 = vec![PossibleStates::NOP];
-    let prod22 = vec![PossibleStates::EXPRESSION, PossibleStates::EXPRESSION_LIST_d];
-    let prod23 = vec![PossibleStates::Terminal(TkType::Operator("OPERATOR{VIRGULA}")), PossibleStates::EXPRESSION, PossibleStates::EXPRESSION_LIST_d];
-    let prod24 = vec![PossibleStates::NOP];
-    let prod25 = vec![PossibleStates::Terminal(TkType::Identifier), PossibleStates::ASSIGNMENT_EXPRESSIONL];
-    let prod26 = vec![PossibleStates::Terminal(TkType::Operator("OPERATOR{IGUAL}")), PossibleStates::EXPRESSION];
-    let prod27 = vec![PossibleStates::Terminal(TkType::Operator("OPERATOR{MAIS_IGUAL}")), PossibleStates::EXPRESSION];
-    let prod28 = vec![PossibleStates::Terminal(TkType::Operator("OPERATOR{MENOS_IGUAL}")), PossibleStates::EXPRESSION];
-    let prod29 = vec![PossibleStates::Terminal(TkType::Operator("OPERATOR{VEZES_IGUAL}")), PossibleStates::EXPRESSION];
-    let prod30 = vec![PossibleStates::Terminal(TkType::Operator("OPERATOR{BARRA_IGUAL}")), PossibleStates::EXPRESSION];
-    let prod31 = vec![PossibleStates::EXPRESSION_A, PossibleStates::EXPRESSIONL];
-    let prod32 = vec![PossibleStates::Terminal(TkType::ReservedWord("RWORD{AND}")), PossibleStates::EXPRESSION];
-    let prod33 = vec![PossibleStates::Terminal(TkType::ReservedWord("RWORD{OR}")), PossibleStates::EXPRESSION];
-    let prod34 = vec![PossibleStates::NOP];
-    let prod35 = vec![PossibleStates::EXPRESSION_B, PossibleStates::EXPRESSION_AL];
-    let prod36 = vec![PossibleStates::Terminal(TkType::ReservedWord("RWORD{NOT}")), PossibleStates::EXPRESSION_B];
-    let prod37 = vec![PossibleStates::Terminal(TkType::Operator("OPERATOR{IGUAL_IGUAL}")), PossibleStates::EXPRESSION_A];
-    let prod38 = vec![PossibleStates::Terminal(TkType::Operator("OPERATOR{DIFERENTE}")), PossibleStates::EXPRESSION_A];
-    let prod39 = vec![PossibleStates::Terminal(TkType::Operator("OPERATOR{MENOR}")), PossibleStates::EXPRESSION_A];
-    let prod40 = vec![PossibleStates::Terminal(TkType::Operator("OPERATOR{MENOR_IGUAL}")), PossibleStates::EXPRESSION_A];
-    let prod41 = vec![PossibleStates::Terminal(TkType::Operator("OPERATOR{MAIOR}")), PossibleStates::EXPRESSION_A];
-    let prod42 = vec![PossibleStates::Terminal(TkType::Operator("OPERATOR{MAIOR_IGUAL}")), PossibleStates::EXPRESSION_A];
-    let prod43 = vec![PossibleStates::NOP];
-    let prod44 = vec![PossibleStates::EXPRESSION_C, PossibleStates::EXPRESSION_BL];
-    let prod45 = vec![PossibleStates::Terminal(TkType::Operator("OPERATOR{VEZES}")), PossibleStates::EXPRESSION_B];
-    let prod46 = vec![PossibleStates::Terminal(TkType::Operator("OPERATOR{BARRA}")), PossibleStates::EXPRESSION_B];
-    let prod47 = vec![PossibleStates::Terminal(TkType::Operator("OPERATOR{CIRCUMFLEXO}")), PossibleStates::EXPRESSION_B];
-    let prod48 = vec![PossibleStates::NOP];
-    let prod49 = vec![PossibleStates::EXPRESSION_D, PossibleStates::EXPRESSION_CL];
-    let prod50 = vec![PossibleStates::Terminal(TkType::Operator("OPERATOR{MAIS}")), PossibleStates::EXPRESSION_C];
-    let prod51 = vec![PossibleStates::Terminal(TkType::Operator("OPERATOR{MENOS}")), PossibleStates::EXPRESSION_C];
-    let prod52 = vec![PossibleStates::NOP];
-    let prod53 = vec![PossibleStates::Terminal(TkType::Operator("OPERATOR{PARENTESES_ESQUERDO}")), PossibleStates::EXPRESSION, PossibleStates::Terminal(TkType::Operator("OPERATOR{PARENTESES_DIREITO}"))];
-    let prod54 = vec![PossibleStates::ID_OR_FCALL];
-    let prod55 = vec![PossibleStates::Terminal(TkType::Literal(LiteralTypes::Int))];
-    let prod56 = vec![PossibleStates::Terminal(TkType::Literal(LiteralTypes::Float))];
-    let prod57 = vec![PossibleStates::Terminal(TkType::Literal(LiteralTypes::String))];
-    let prod58 = vec![PossibleStates::Terminal(TkType::ReservedWord("RWORD{ELSE}")), PossibleStates::Terminal(TkType::Operator("OPERATOR{DOIS_PONTOS}")), PossibleStates::Terminal(TkType::EOS), PossibleStates::SCOPE];
-    let prod59 = vec![PossibleStates::Terminal(TkType::ReservedWord("RWORD{ELIF}")), PossibleStates::EXPRESSION, PossibleStates::Terminal(TkType::Operator("OPERATOR{DOIS_PONTOS}")), PossibleStates::Terminal(TkType::EOS), PossibleStates::SCOPE, PossibleStates::ELSE_STATEMENT];
-    let prod60 = vec![PossibleStates::NOP];
-
-    // dedent
-    hm.insert(HmIndex {
-        state: PossibleStates::STATEMENT_LIST_E,
-        token: TkType::Dedentation
-    }, prod3.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::ELSE_STATEMENT,
-        token: TkType::Dedentation
-    }, prod60.clone());
-
-    // indent
-    hm.insert(HmIndex {
-        state: PossibleStates::SCOPE,
-        token: TkType::Indentaion
-    }, prod4.clone());
-
-    // def
-    hm.insert(HmIndex {
-        state: PossibleStates::STATEMENT_LIST,
-        token: TkType::ReservedWord("RWORD{DEF}")
-    }, prod1.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::STATEMENT,
-        token: TkType::ReservedWord("RWORD{DEF}")
-    }, prod5.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::STATEMENT_LIST_E,
-        token: TkType::ReservedWord("RWORD{DEF}")
-    }, prod2.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::ELSE_STATEMENT,
-        token: TkType::ReservedWord("RWORD{DEF}")
-    }, prod60.clone());
-
-    // id
-    hm.insert(HmIndex {
-        state: PossibleStates::STATEMENT_LIST,
-        token: TkType::Identifier
-    }, prod1.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::STATEMENT,
-        token: TkType::Identifier
-    }, prod9.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::STATEMENT_LIST_E,
-        token: TkType::Identifier
-    }, prod2.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::ID_LIST,
-        token: TkType::Identifier
-    }, prod13.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_LIST,
-        token: TkType::Identifier
-    }, prod22.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_STATEMENT,
-        token: TkType::Identifier
-    }, prod16.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION,
-        token: TkType::Identifier
-    }, prod31.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::ELSE_STATEMENT,
-        token: TkType::Identifier
-    }, prod60.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::ID_OR_FCALL,
-        token: TkType::Identifier
-    }, prod19.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::ASSIGNMENT_EXPRESSION,
-        token: TkType::Identifier
-    }, prod25.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_A,
-        token: TkType::Identifier
-    }, prod35.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_B,
-        token: TkType::Identifier
-    }, prod44.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_C,
-        token: TkType::Identifier
-    }, prod49.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_D,
-        token: TkType::Identifier
-    }, prod54.clone());
-
-    // (
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_LIST,
-        token: TkType::Operator("OPERATOR{PARENTESES_ESQUERDO}")
-    }, prod22.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION,
-        token: TkType::Operator("OPERATOR{PARENTESES_ESQUERDO}")
-    }, prod31.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_STATEMENTL,
-        token: TkType::Operator("OPERATOR{PARENTESES_ESQUERDO}")
-    }, prod17.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::ID_OR_FCALL_D,
-        token: TkType::Operator("OPERATOR{PARENTESES_ESQUERDO}")
-    }, prod20.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_A,
-        token: TkType::Operator("OPERATOR{PARENTESES_ESQUERDO}")
-    }, prod35.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_B,
-        token: TkType::Operator("OPERATOR{PARENTESES_ESQUERDO}")
-    }, prod44.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_B,
-        token: TkType::Operator("OPERATOR{PARENTESES_ESQUERDO}")
-    }, prod49.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_D,
-        token: TkType::Operator("OPERATOR{PARENTESES_ESQUERDO}")
-    }, prod53.clone());
-
-    // )
-    hm.insert(HmIndex {
-        state: PossibleStates::ID_LIST_D,
-        token: TkType::Operator("OPERATOR{PARENTESES_DIREITO}")
-    }, prod15.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::ID_OR_FCALL_D,
-        token: TkType::Operator("OPERATOR{PARENTESES_DIREITO}")
-    }, prod21.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_LIST_d,
-        token: TkType::Operator("OPERATOR{PARENTESES_DIREITO}")
-    }, prod24.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSIONL,
-        token: TkType::Operator("OPERATOR{PARENTESES_DIREITO}")
-    }, prod34.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_AL,
-        token: TkType::Operator("OPERATOR{PARENTESES_DIREITO}")
-    }, prod43.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_BL,
-        token: TkType::Operator("OPERATOR{PARENTESES_DIREITO}")
-    }, prod48.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_CL,
-        token: TkType::Operator("OPERATOR{PARENTESES_DIREITO}")
-    }, prod52.clone());
-
-    // :
-    hm.insert(HmIndex {
-        state: PossibleStates::ID_OR_FCALL_D,
-        token: TkType::Operator("OPERATOR{DOIS_PONTOS}")
-    }, prod21.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSIONL,
-        token: TkType::Operator("OPERATOR{DOIS_PONTOS}")
-    }, prod34.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_AL,
-        token: TkType::Operator("OPERATOR{DOIS_PONTOS}")
-    }, prod43.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_BL,
-        token: TkType::Operator("OPERATOR{DOIS_PONTOS}")
-    }, prod48.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_CL,
-        token: TkType::Operator("OPERATOR{DOIS_PONTOS}")
-    }, prod52.clone());
-
-    // EOS
-    hm.insert(HmIndex {
-        state: PossibleStates::ID_OR_FCALL_D,
-        token: TkType::EOS
-    }, prod21.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_LIST_d,
-        token: TkType::EOS
-    }, prod24.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSIONL,
-        token: TkType::EOS
-    }, prod34.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_AL,
-        token: TkType::EOS
-    }, prod43.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_BL,
-        token: TkType::EOS
-    }, prod48.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_CL,
-        token: TkType::EOS
-    }, prod52.clone());
-
-    // ,
-    hm.insert(HmIndex {
-        state: PossibleStates::ID_LIST_D,
-        token: TkType::Operator("OPERATOR{VIRGULA}")
-    }, prod14.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::ID_OR_FCALL_D,
-        token: TkType::Operator("OPERATOR{VIRGULA}")
-    }, prod21.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_LIST_d,
-        token: TkType::Operator("OPERATOR{VIRGULA}")
-    }, prod23.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSIONL,
-        token: TkType::Operator("OPERATOR{VIRGULA}")
-    }, prod34.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_AL,
-        token: TkType::Operator("OPERATOR{VIRGULA}")
-    }, prod43.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_BL,
-        token: TkType::Operator("OPERATOR{VIRGULA}")
-    }, prod48.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_CL,
-        token: TkType::Operator("OPERATOR{VIRGULA}")
-    }, prod52.clone());
-
-    // and
-    hm.insert(HmIndex {
-        state: PossibleStates::ID_OR_FCALL_D,
-        token: TkType::ReservedWord("RWORD{AND}")
-    }, prod21.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSIONL,
-        token: TkType::ReservedWord("RWORD{AND}")
-    }, prod33.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_AL,
-        token: TkType::ReservedWord("RWORD{AND}")
-    }, prod43.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_BL,
-        token: TkType::ReservedWord("RWORD{AND}")
-    }, prod48.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_CL,
-        token: TkType::ReservedWord("RWORD{AND}")
-    }, prod52.clone());
-
-    // or
-    hm.insert(HmIndex {
-        state: PossibleStates::ID_OR_FCALL_D,
-        token: TkType::ReservedWord("RWORD{OR}")
-    }, prod21.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSIONL,
-        token: TkType::ReservedWord("RWORD{OR}")
-    }, prod33.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_AL,
-        token: TkType::ReservedWord("RWORD{OR}")
-    }, prod43.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_BL,
-        token: TkType::ReservedWord("RWORD{OR}")
-    }, prod48.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_CL,
-        token: TkType::ReservedWord("RWORD{OR}")
-    }, prod52.clone());
-
-    // ==
-    hm.insert(HmIndex {
-        state: PossibleStates::ID_OR_FCALL_D,
-        token: TkType::Operator("OPERATOR{IGUAL_IGUAL}")
-    }, prod21.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_AL,
-        token: TkType::Operator("OPERATOR{IGUAL_IGUAL}")
-    }, prod37.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_BL,
-        token: TkType::Operator("OPERATOR{IGUAL_IGUAL}")
-    }, prod48.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_CL,
-        token: TkType::Operator("OPERATOR{IGUAL_IGUAL}")
-    }, prod52.clone());
-
-    // !=
-    hm.insert(HmIndex {
-        state: PossibleStates::ID_OR_FCALL_D,
-        token: TkType::Operator("OPERATOR{DIFERENTE}")
-    }, prod21.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_AL,
-        token: TkType::Operator("OPERATOR{DIFERENTE}")
-    }, prod38.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_BL,
-        token: TkType::Operator("OPERATOR{DIFERENTE}")
-    }, prod48.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_CL,
-        token: TkType::Operator("OPERATOR{DIFERENTE}")
-    }, prod52.clone());
-
-    // <
-    hm.insert(HmIndex {
-        state: PossibleStates::ID_OR_FCALL_D,
-        token: TkType::Operator("OPERATOR{MENOR}")
-    }, prod21.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_AL,
-        token: TkType::Operator("OPERATOR{MENOR}")
-    }, prod39.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_BL,
-        token: TkType::Operator("OPERATOR{MENOR}")
-    }, prod48.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_CL,
-        token: TkType::Operator("OPERATOR{MENOR}")
-    }, prod52.clone());
-
-    // <=
-    hm.insert(HmIndex {
-        state: PossibleStates::ID_OR_FCALL_D,
-        token: TkType::Operator("OPERATOR{MENOR_IGUAL}")
-    }, prod21.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_AL,
-        token: TkType::Operator("OPERATOR{MENOR_IGUAL}")
-    }, prod40.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_BL,
-        token: TkType::Operator("OPERATOR{MENOR_IGUAL}")
-    }, prod48.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_CL,
-        token: TkType::Operator("OPERATOR{MENOR_IGUAL}")
-    }, prod52.clone());
-
-    // >
-    hm.insert(HmIndex {
-        state: PossibleStates::ID_OR_FCALL_D,
-        token: TkType::Operator("OPERATOR{MAIOR}")
-    }, prod21.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_AL,
-        token: TkType::Operator("OPERATOR{MAIOR}")
-    }, prod41.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_BL,
-        token: TkType::Operator("OPERATOR{MAIOR}")
-    }, prod48.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_CL,
-        token: TkType::Operator("OPERATOR{MAIOR}")
-    }, prod52.clone());
-
-    // >=
-    hm.insert(HmIndex {
-        state: PossibleStates::ID_OR_FCALL_D,
-        token: TkType::Operator("OPERATOR{MAIOR_IGUAL}")
-    }, prod21.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_AL,
-        token: TkType::Operator("OPERATOR{MAIOR_IGUAL}")
-    }, prod42.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_BL,
-        token: TkType::Operator("OPERATOR{MAIOR_IGUAL}")
-    }, prod48.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_CL,
-        token: TkType::Operator("OPERATOR{MAIOR_IGUAL}")
-    }, prod52.clone());
-
-    // *
-    hm.insert(HmIndex {
-        state: PossibleStates::ID_OR_FCALL_D,
-        token: TkType::Operator("OPERATOR{VEZES}")
-    }, prod21.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_BL,
-        token: TkType::Operator("OPERATOR{VEZES}")
-    }, prod45.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_CL,
-        token: TkType::Operator("OPERATOR{VEZES}")
-    }, prod52.clone());
-
-    // /
-    hm.insert(HmIndex {
-        state: PossibleStates::ID_OR_FCALL_D,
-        token: TkType::Operator("OPERATOR{BARRA}")
-    }, prod21.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_BL,
-        token: TkType::Operator("OPERATOR{BARRA}")
-    }, prod46.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_CL,
-        token: TkType::Operator("OPERATOR{BARRA}")
-    }, prod52.clone());
-
-    // ^
-    hm.insert(HmIndex {
-        state: PossibleStates::ID_OR_FCALL_D,
-        token: TkType::Operator("OPERATOR{CIRCUMFLEXO}")
-    }, prod21.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_BL,
-        token: TkType::Operator("OPERATOR{CIRCUMFLEXO}")
-    }, prod47.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_CL,
-        token: TkType::Operator("OPERATOR{CIRCUMFLEXO}")
-    }, prod52.clone());
-
-    // +
-    hm.insert(HmIndex {
-        state: PossibleStates::ID_OR_FCALL_D,
-        token: TkType::Operator("OPERATOR{MAIS}")
-    }, prod21.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_CL,
-        token: TkType::Operator("OPERATOR{MAIS}")
-    }, prod50.clone());
-
-    // -
-    hm.insert(HmIndex {
-        state: PossibleStates::ID_OR_FCALL_D,
-        token: TkType::Operator("OPERATOR{MENOS}")
-    }, prod21.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_CL,
-        token: TkType::Operator("OPERATOR{MENOS}")
-    }, prod51.clone());
-
-    // =
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_STATEMENTL,
-        token: TkType::Operator("OPERATOR{IGUAL}")
-    }, prod18.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::ASSIGNMENT_EXPRESSIONL,
-        token: TkType::Operator("OPERATOR{IGUAL}")
-    }, prod26.clone());
-
-    // +=
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_STATEMENTL,
-        token: TkType::Operator("OPERATOR{MAIS_IGUAL}")
-    }, prod18.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::ASSIGNMENT_EXPRESSIONL,
-        token: TkType::Operator("OPERATOR{MAIS_IGUAL}")
-    }, prod27.clone());
-
-    // -=
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_STATEMENTL,
-        token: TkType::Operator("OPERATOR{MENOS_IGUAL}")
-    }, prod18.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::ASSIGNMENT_EXPRESSIONL,
-        token: TkType::Operator("OPERATOR{MENOS_IGUAL}")
-    }, prod28.clone());
-
-    // *=
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_STATEMENTL,
-        token: TkType::Operator("OPERATOR{VEZES_IGUAL}")
-    }, prod18.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::ASSIGNMENT_EXPRESSIONL,
-        token: TkType::Operator("OPERATOR{VEZES_IGUAL}")
-    }, prod29.clone());
-
-    // /=
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_STATEMENTL,
-        token: TkType::Operator("OPERATOR{BARRA_IGUAL}")
-    }, prod18.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::ASSIGNMENT_EXPRESSIONL,
-        token: TkType::Operator("OPERATOR{BARRA_IGUAL}")
-    }, prod30.clone());
-
-    // not
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_LIST,
-        token: TkType::ReservedWord("RWORD{NOT}")
-    }, prod22.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION,
-        token: TkType::ReservedWord("RWORD{NOT}")
-    }, prod31.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_A,
-        token: TkType::ReservedWord("RWORD{NOT}")
-    }, prod36.clone());
-
-    // int
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_LIST,
-        token: TkType::Literal(LiteralTypes::Int)
-    }, prod22.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION,
-        token: TkType::Literal(LiteralTypes::Int)
-    }, prod31.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_A,
-        token: TkType::Literal(LiteralTypes::Int)
-    }, prod35.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_B,
-        token: TkType::Literal(LiteralTypes::Int)
-    }, prod44.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_C,
-        token: TkType::Literal(LiteralTypes::Int)
-    }, prod49.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_D,
-        token: TkType::Literal(LiteralTypes::Int)
-    }, prod55.clone());
-
-    // float
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_LIST,
-        token: TkType::Literal(LiteralTypes::Float)
-    }, prod22.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION,
-        token: TkType::Literal(LiteralTypes::Float)
-    }, prod31.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_A,
-        token: TkType::Literal(LiteralTypes::Float)
-    }, prod35.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_B,
-        token: TkType::Literal(LiteralTypes::Float)
-    }, prod44.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_C,
-        token: TkType::Literal(LiteralTypes::Float)
-    }, prod49.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_D,
-        token: TkType::Literal(LiteralTypes::Float)
-    }, prod56.clone());
-
-    // string
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_LIST,
-        token: TkType::Literal(LiteralTypes::String)
-    }, prod22.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION,
-        token: TkType::Literal(LiteralTypes::String)
-    }, prod31.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_A,
-        token: TkType::Literal(LiteralTypes::String)
-    }, prod35.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_B,
-        token: TkType::Literal(LiteralTypes::String)
-    }, prod44.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_C,
-        token: TkType::Literal(LiteralTypes::String)
-    }, prod49.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::EXPRESSION_D,
-        token: TkType::Literal(LiteralTypes::String)
-    }, prod57.clone());
-
-    // break
-    hm.insert(HmIndex {
-        state: PossibleStates::STATEMENT_LIST,
-        token: TkType::ReservedWord("RWORD{BREAK}")
-    }, prod1.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::STATEMENT,
-        token: TkType::ReservedWord("RWORD{BREAK}")
-    }, prod6.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::STATEMENT_LIST_E,
-        token: TkType::ReservedWord("RWORD{BREAK}")
-    }, prod2.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::ELSE_STATEMENT,
-        token: TkType::ReservedWord("RWORD{BREAK}")
-    }, prod60.clone());
-
-    // continue
-    hm.insert(HmIndex {
-        state: PossibleStates::STATEMENT_LIST,
-        token: TkType::ReservedWord("RWORD{CONTINUE}")
-    }, prod1.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::STATEMENT,
-        token: TkType::ReservedWord("RWORD{CONTINUE}")
-    }, prod7.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::STATEMENT_LIST_E,
-        token: TkType::ReservedWord("RWORD{CONTINUE}")
-    }, prod2.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::ELSE_STATEMENT,
-        token: TkType::ReservedWord("RWORD{CONTINUE}")
-    }, prod60.clone());
-
-    // return
-    hm.insert(HmIndex {
-        state: PossibleStates::STATEMENT_LIST,
-        token: TkType::ReservedWord("RWORD{RETURN}")
-    }, prod1.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::STATEMENT,
-        token: TkType::ReservedWord("RWORD{RETURN}")
-    }, prod8.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::STATEMENT_LIST_E,
-        token: TkType::ReservedWord("RWORD{RETURN}")
-    }, prod2.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::ELSE_STATEMENT,
-        token: TkType::ReservedWord("RWORD{RETURN}")
-    }, prod60.clone());
-
-    // for
-    hm.insert(HmIndex {
-        state: PossibleStates::STATEMENT_LIST,
-        token: TkType::ReservedWord("RWORD{FOR}")
-    }, prod1.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::STATEMENT,
-        token: TkType::ReservedWord("RWORD{FOR}")
-    }, prod10.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::STATEMENT_LIST_E,
-        token: TkType::ReservedWord("RWORD{FOR}")
-    }, prod2.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::ELSE_STATEMENT,
-        token: TkType::ReservedWord("RWORD{FOR}")
-    }, prod60.clone());
-
-    // while
-    hm.insert(HmIndex {
-        state: PossibleStates::STATEMENT_LIST,
-        token: TkType::ReservedWord("RWORD{WHILE}")
-    }, prod1.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::STATEMENT,
-        token: TkType::ReservedWord("RWORD{WHILE}")
-    }, prod11.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::STATEMENT_LIST_E,
-        token: TkType::ReservedWord("RWORD{WHILE}")
-    }, prod2.clone());
-
-    // if
-    hm.insert(HmIndex {
-        state: PossibleStates::STATEMENT_LIST,
-        token: TkType::ReservedWord("RWORD{IF}")
-    }, prod1.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::STATEMENT,
-        token: TkType::ReservedWord("RWORD{IF}")
-    }, prod12.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::STATEMENT_LIST_E,
-        token: TkType::ReservedWord("RWORD{IF}")
-    }, prod2.clone());
-
-    // else
-    hm.insert(HmIndex {
-        state: PossibleStates::ELSE_STATEMENT,
-        token: TkType::ReservedWord("RWORD{ELSE}")
-    }, prod58.clone());
-
-    // elif
-    hm.insert(HmIndex {
-        state: PossibleStates::ELSE_STATEMENT,
-        token: TkType::ReservedWord("RWORD{ELIF}")
-    }, prod59.clone());
-
-    // $
-    hm.insert(HmIndex {
-        state: PossibleStates::STATEMENT_LIST_E,
-        token: TkType::END
-    }, prod3.clone());
-    hm.insert(HmIndex {
-        state: PossibleStates::ELSE_STATEMENT,
-        token: TkType::END
-    }, prod60.clone());
+    for (state, prod) in productions.iter() {
+        let (mut set, is_nullable) = sequence_first(prod, &first, &nullable);
+
+        if is_nullable {
+            if let Some(f) = follow.get(state) {
+                set.extend(f.iter().cloned());
+            }
+        }
+
+        for tk_type in set {
+            let index = HmIndex {
+                state: *state,
+                token: tk_type
+            };
+
+            if let Some(other) = hm.insert(index, prod.clone()) {
+                panic!("LL(1) conflict on {:?} with {:?}: {:?} and {:?}", state, tk_type, other, prod);
+            }
+        }
+    }
 
     hm
 }
 
-fn parse(tokens: &Vec<Token>) -> Result<(), CompilationError> {
-    let hm = generate_lookup_table();
-    let mut stack = Vec::<PossibleStates>::new();
+#[derive(Debug)]
+struct ParseNode {
+    state: PossibleStates,
+    token: Option<usize>,
+    children: Vec<usize>
+}
+
+struct ParseTree<'a> {
+    tokens: &'a [Token],
+    nodes: Vec<ParseNode>,
+    root: usize
+}
+
+impl<'a> ParseTree<'a> {
+    fn add_node(&mut self, state: PossibleStates) -> usize {
+        self.nodes.push(ParseNode {
+            state,
+            token: None,
+            children: Vec::new()
+        });
+
+        self.nodes.len() - 1
+    }
+
+    fn state(&self, node: usize) -> PossibleStates {
+        self.nodes[node].state
+    }
+
+    fn children(&self, node: usize) -> &[usize] {
+        &self.nodes[node].children
+    }
+
+    fn token(&self, node: usize) -> &'a Token {
+        match self.nodes[node].token {
+            Some(i) => &self.tokens[i],
+            None => panic!("Node {:?} has no token", self.nodes[node].state)
+        }
+    }
+
+    // os nos nao guardam o pai, so e usado nas mensagens de erro
+    fn parent(&self, node: usize) -> Option<usize> {
+        self.nodes.iter().position(|n| n.children.contains(&node))
+    }
+
+    // sem recursao, a arvore pode ser bem funda
+    fn first_token(&self, node: usize) -> Option<&'a Token> {
+        let mut pending = vec![node];
+
+        while let Some(node) = pending.pop() {
+            if self.nodes[node].token.is_some() {
+                return Some(self.token(node));
+            }
+
+            pending.extend(self.children(node).iter().rev());
+        }
+
+        None
+    }
+
+    fn last_token(&self, node: usize) -> Option<&'a Token> {
+        let mut pending = vec![node];
+
+        while let Some(node) = pending.pop() {
+            if self.nodes[node].token.is_some() {
+                return Some(self.token(node));
+            }
+
+            pending.extend(self.children(node));
+        }
+
+        None
+    }
+
+    fn has_tokens(&self, node: usize) -> bool {
+        self.first_token(node).is_some()
+    }
+}
+
+// nome dos nao terminais que aparecem no contexto das mensagens de erro
+fn state_description(state: PossibleStates) -> Option<&'static str> {
+    match state {
+        PossibleStates::PARAMETERS | PossibleStates::LAMBDA_PARAMETERS => Some("parameter list"),
+        PossibleStates::PARAMETER | PossibleStates::LAMBDA_PARAMETER => Some("parameter"),
+        PossibleStates::CALL_ARGUMENTS => Some("argument list"),
+        PossibleStates::ARGUMENT => Some("argument"),
+        PossibleStates::SUBSCRIPT_LIST => Some("subscript"),
+        PossibleStates::TARGET_LIST => Some("target list"),
+        PossibleStates::ID_LIST => Some("name list"),
+        PossibleStates::TYPE_PARAM_LIST | PossibleStates::TYPE_PARAM => Some("type parameter list"),
+        PossibleStates::WITH_ITEM => Some("with item"),
+        PossibleStates::DECORATORS => Some("decorator"),
+        PossibleStates::SUBJECT => Some("match subject"),
+        PossibleStates::PATTERNS | PossibleStates::PATTERN => Some("pattern"),
+        PossibleStates::EXPRESSION |
+        PossibleStates::NAMED_EXPRESSION |
+        PossibleStates::STAR_EXPRESSIONS |
+        PossibleStates::EXPRESSION_LIST |
+        PossibleStates::DISJUNCTION |
+        PossibleStates::BITWISE_OR => Some("expression"),
+        _ => None
+    }
+}
+
+// como o token aparece depois de "found"
+fn token_description(tk: &Token) -> String {
+    match tk.tk_type {
+        TkType::Identifier => format!("identifier '{}'", tk.lexema),
+        TkType::Literal(LiteralTypes::String) => format!("string {}", tk.lexema),
+        TkType::Literal(_) => format!("{} '{}'", token_name(tk.tk_type), tk.lexema),
+        TkType::ReservedWord(_) => format!("keyword '{}'", tk.lexema),
+        tk_type => token_name(tk_type)
+    }
+}
+
+// tokens aceitos com a pilha atual: FIRST do topo, descendo enquanto o simbolo for anulavel
+fn expected_tokens(stack: &[(PossibleStates, usize)], target: Version) -> String {
+    let (first, nullable) = grammar_first_sets();
+    let states: Vec<PossibleStates> = stack.iter().rev().map(|s| s.0).collect();
+    let (mut expected, _) = sequence_first(&states, first, nullable);
+
+    // palavras que nao sao reservadas na versao escolhida nao aparecem, e uma palavra reservada
+    // so no inicio de um comando nao aparece onde um identificador tambem serviria
+    let identifier = expected.contains(&TkType::Identifier);
+    let visible = |t: &TkType| match t {
+        TkType::ReservedWord(id) if SOFT_KEYWORDS.iter().any(|w| w.1 == *id) => !identifier,
+        TkType::ReservedWord(id) => RESERVED_WORDS.iter().find(|w| w.1 == *id).is_none_or(|w| is_reserved_word(w.0, target)),
+        _ => true
+    };
+    expected.retain(|t| visible(t));
+
+    // listas longas demais viram "statement", "expression" e "operator", do grupo maior para o menor
+    let union = |states: &[PossibleStates]| -> HashSet<TkType> {
+        states.iter().flat_map(|s| first[s].iter().cloned()).filter(|t| visible(t)).collect()
+    };
+    let arithmetic = [
+        PossibleStates::BITWISE_ORL,
+        PossibleStates::BITWISE_XORL,
+        PossibleStates::BITWISE_ANDL,
+        PossibleStates::SHIFT_EXPRESSIONL,
+        PossibleStates::SUML,
+        PossibleStates::TERML,
+        PossibleStates::POWERL
+    ];
+    let mut operators = arithmetic.to_vec();
+    operators.extend(&[PossibleStates::DISJUNCTIONL, PossibleStates::CONJUNCTIONL, PossibleStates::COMPARISON_OPERATOR]);
+
+    let groups = [
+        ("statement", union(&[PossibleStates::STATEMENT])),
+        ("expression", union(&[PossibleStates::EXPRESSION])),
+        ("expression", union(&[PossibleStates::FACTOR])),
+        ("operator", union(&operators)),
+        ("operator", union(&arithmetic))
+    ];
+
+    let mut names: Vec<String> = Vec::new();
+
+    for (name, group) in groups.iter() {
+        if group.is_subset(&expected) {
+            names.push(name.to_string());
+            expected.retain(|t| !group.contains(t));
+        }
+    }
+
+    let mut tokens: Vec<String> = expected.into_iter().map(token_name).collect();
+    tokens.sort();
+    names.extend(tokens);
+
+    match names.len() {
+        1 => names.remove(0),
+        2 => format!("{} or {}", names[0], names[1]),
+        _ => format!("one of {}", names.join(", "))
+    }
+}
+
+// "after parameter list": o irmao anterior mais proximo que tem descricao ou e um token ja lido,
+// ou a construcao que contem o erro
+fn error_context(tree: &ParseTree, node: usize) -> String {
+    let mut node = node;
+
+    while let Some(parent) = tree.parent(node) {
+        let siblings = tree.children(parent);
+        let k = siblings.iter().position(|&c| c == node).unwrap();
+
+        for &sibling in siblings[..k].iter().rev() {
+            match tree.state(sibling) {
+                // o que vem antes de um ) ] } descreve melhor o contexto
+                PossibleStates::Terminal(TkType::Operator("OPERATOR{PARENTESES_DIREITO}")) |
+                PossibleStates::Terminal(TkType::Operator("OPERATOR{COLCHETES_DIREITO}")) |
+                PossibleStates::Terminal(TkType::Operator("OPERATOR{CHAVES_DIREITA}")) => continue,
+                PossibleStates::Terminal(tk_type) => {
+                    let tk = tree.token(sibling);
+
+                    return match tk.lexema.as_str() {
+                        "" => format!(" after {}", token_name(tk_type)),
+                        lexema => format!(" after '{}'", lexema)
+                    };
+                },
+                state => {
+                    if let Some(description) = state_description(state) {
+                        return format!(" after {}", description);
+                    }
+                }
+            }
+        }
+
+        node = parent;
+
+        // ja dentro de uma construcao que consumiu tokens: "after expression"
+        if let Some(description) = state_description(tree.state(node)) {
+            if tree.has_tokens(node) {
+                return format!(" after {}", description);
+            }
+        }
+    }
+
+    String::new()
+}
+
+// quantos tokens a pilha consegue consumir, sem montar a arvore
+fn consumed(hm: &HashMap<HmIndex, Vec<PossibleStates>>, stack: &[(PossibleStates, usize)], tokens: &[TkType]) -> usize {
+    let mut stack: Vec<PossibleStates> = stack.iter().map(|s| s.0).collect();
+
+    for (n, &tk_type) in tokens.iter().enumerate() {
+        loop {
+            let last_state = match stack.last() {
+                Some(&top) => top,
+                None => return n
+            };
+
+            if let PossibleStates::Terminal(expected) = last_state {
+                if expected != tk_type {
+                    return n;
+                }
+
+                stack.pop();
+                break;
+            }
+
+            match hm.get(&HmIndex { state: last_state, token: tk_type }) {
+                Some(p) => {
+                    stack.pop();
+                    stack.extend(p.iter().rev().filter(|&&s| s != PossibleStates::NOP));
+                },
+                None => return n
+            }
+        }
+    }
+
+    tokens.len()
+}
+
+// tokens que uma correcao pode inserir, na ordem de preferencia
+static REPAIR_TOKENS: [&str; 4] = [",", ":", "in", "="];
+
+// o parser sozinho nao ve erros da lowering, como `lambda x, x: 1`, nem construcoes mais novas que o target
+fn accepted(tokens: &[Token], target: Version) -> bool {
+    parse(tokens, target, false, false)
+        .and_then(|tree| ast::lower(&tree))
+        .and_then(|module| version::check(&module, target))
+        .is_ok()
+}
+
+// a menor correcao no ponto do erro: um token a mais antes de tokens[i] ou tokens[i] a menos.
+// A tabela LL(1) descarta as que nao passam de alguns tokens depois do erro; das outras vale a que
+// deixa o arquivo inteiro valido, e so e machine-applicable se for a unica
+fn repair(
+    hm: &HashMap<HmIndex, Vec<PossibleStates>>,
+    stack: &[(PossibleStates, usize)],
+    tokens: &[Token],
+    i: usize,
+    target: Version
+) -> Option<Suggestion> {
+    let tk = &tokens[i];
+    let rest: Vec<TkType> = tokens[i..].iter().map(|t| t.tk_type).collect();
+    let enough = rest.len().min(4);
+    // ate onde a tabela chegou, a correcao e o arquivo com ela aplicada
+    let mut candidates: Vec<(usize, Suggestion, Vec<Token>)> = Vec::new();
+
+    // logo depois do token anterior, para ficar "f(x):" e nao "f(x) :"
+    let (row, col) = match i.checked_sub(1).map(|p| &tokens[p]) {
+        Some(prev) if !prev.lexema.is_empty() => (prev.row, prev.col + prev.lexema.chars().count()),
+        _ => (tk.row, tk.col)
+    };
+    let at_end_of_line = tk.tk_type == TkType::EOS || tk.row > row;
+
+    for lexema in REPAIR_TOKENS.iter() {
+        let tk_type = match rword_or_op(lexema) {
+            PossibleStates::Terminal(tk_type) => tk_type,
+            _ => continue
+        };
+
+        let mut sequence = vec![tk_type];
+        sequence.extend(&rest);
+
+        let reached = consumed(hm, stack, &sequence).saturating_sub(1);
+
+        if reached < enough {
+            continue;
+        }
+
+        let mut replacement = lexema.to_string();
+
+        // palavras reservadas precisam de espaco dos dois lados
+        if lexema.chars().all(|c| c.is_ascii_alphabetic()) {
+            replacement.insert(0, ' ');
+
+            if !at_end_of_line && tk.col == col {
+                replacement.push(' ');
+            }
+        }
+
+        let message = if at_end_of_line {
+            format!("insert '{}' at end of line {}", lexema, row + 1)
+        } else {
+            format!("insert '{}' before {}", lexema, token_description(tk))
+        };
+
+        let mut fixed = tokens.to_vec();
+        fixed.insert(i, Token::new(tk_type, lexema.to_string(), row, col));
+
+        let suggestion = Suggestion { message, row, col, len: 0, replacement, applicability: Applicability::MaybeIncorrect };
+        candidates.push((reached, suggestion, fixed));
+    }
+
+    if !tk.lexema.is_empty() {
+        let reached = consumed(hm, stack, &rest[1..]) + 1;
+
+        if reached >= enough {
+            let message = format!("remove {}", token_description(tk));
+            let len = tk.lexema.chars().count();
+
+            let mut fixed = tokens.to_vec();
+            fixed.remove(i);
+
+            let suggestion = Suggestion { message, row: tk.row, col: tk.col, len, replacement: String::new(), applicability: Applicability::MaybeIncorrect };
+            candidates.push((reached, suggestion, fixed));
+        }
+    }
+
+    let valid: Vec<bool> = candidates.iter().map(|c| accepted(&c.2, target)).collect();
+    let unique = valid.iter().filter(|&&v| v).count() == 1;
+
+    // validas primeiro, depois a que foi mais longe; no empate fica a primeira da lista
+    let best = (0..candidates.len()).rev().max_by_key(|&k| (valid[k], candidates[k].0))?;
+    let (_, mut suggestion, _) = candidates.swap_remove(best);
+
+    if valid[best] && unique {
+        suggestion.applicability = Applicability::MachineApplicable;
+    }
+
+    Some(suggestion)
+}
+
+fn unexpected_token(
+    tree: &ParseTree,
+    stack: &[(PossibleStates, usize)],
+    hm: &HashMap<HmIndex, Vec<PossibleStates>>,
+    i: usize,
+    target: Version,
+    suggest: bool
+) -> CompilationError {
+    let tk = &tree.tokens[i];
+    let expected = expected_tokens(stack, target);
+    let context = error_context(tree, stack[stack.len() - 1].1);
+
+    // x = 1 + no fim do arquivo para no newline, mas so sobram newline, dedent e o fim
+    let at_end = tree.tokens[i..].iter().all(|t| matches!(t.tk_type, TkType::EOS | TkType::Dedentation | TkType::END));
+
+    let (code, message) = if at_end {
+        (ErrorCode::UnexpectedEof, format!("unexpected end of file, expected {}{}", expected, context))
+    } else {
+        (ErrorCode::UnexpectedToken, format!("expected {}{}, found {}", expected, context, token_description(tk)))
+    };
+
+    let mut diagnostic = Diagnostic::new(code, &message, tk.row, tk.col, tk.lexema.chars().count());
 
-    println!("empilha $");
-    stack.push(PossibleStates::Terminal(TkType::END));
-    println!("empilha produção inicial");
-    stack.push(PossibleStates::STATEMENT_LIST);
+    if let Some(suggestion) = suggest.then(|| repair(hm, stack, tree.tokens, i, target)).flatten() {
+        diagnostic = diagnostic.with_suggestion(suggestion);
+    }
 
-    for tk in tokens.iter() {
-        println!("token {:?}", tk);
+    CompilationError::SintaxError(Box::new(diagnostic))
+}
+
+// verbose mostra cada passo da pilha na saida padrao; suggest procura uma correcao para o erro,
+// desligado quando o proprio parse testa uma correcao
+fn parse(tokens: &[Token], target: Version, verbose: bool, suggest: bool) -> Result<ParseTree<'_>, CompilationError> {
+    macro_rules! trace {
+        ($($arg:tt)*) => {
+            if verbose {
+                println!($($arg)*);
+            }
+        };
+    }
+
+    let hm = lookup_table();
+    let mut tree = ParseTree {
+        tokens,
+        nodes: Vec::new(),
+        root: 0
+    };
+    let mut stack = Vec::<(PossibleStates, usize)>::new();
+
+    trace!("empilha $");
+    let end = tree.add_node(PossibleStates::Terminal(TkType::END));
+    stack.push((PossibleStates::Terminal(TkType::END), end));
+    trace!("empilha produção inicial");
+    tree.root = tree.add_node(PossibleStates::FILE);
+    stack.push((PossibleStates::FILE, tree.root));
+
+    for (i, tk) in tokens.iter().enumerate() {
+        trace!("token {:?}", tk);
 
         loop {
-            let last_state = stack.last().unwrap().clone();
+            let (last_state, node) = match stack.last() {
+                Some(&top) => top,
+                None => {
+                    let message = format!("unexpected {} after end of file", token_name(tk.tk_type));
+                    return Err(syntax_error(ErrorCode::UnexpectedToken, &message, tk.row, tk.col, tk.lexema.chars().count()));
+                }
+            };
 
             if let PossibleStates::Terminal(tk_type) = last_state {
                 if tk.tk_type != tk_type {
-                    return Err(CompilationError::SintaxError(format!("Token mismatch, expected, '{:?}' but found '{:?}', at: row {}, col {}", tk_type, tk.tk_type, tk.row, tk.col)));
+                    return Err(unexpected_token(&tree, &stack, hm, i, target, suggest));
                 }
-                
+
+                tree.nodes[node].token = Some(i);
                 stack.pop();
                 break;
             }
 
             let prox = HmIndex {
                 state: last_state,
-                token: tk.tk_type.clone()
+                token: tk.tk_type
             };
 
-            println!("prox {:?}", prox);
+            trace!("prox {:?}", prox);
 
             match hm.get(&prox) {
                 Some(p) => {
                     stack.pop();
-                    println!("desempilha");
-                    
-                    for s in p.iter().rev() {
+                    trace!("desempilha");
+
+                    let mut children = Vec::new();
+
+                    for s in p.iter() {
                         let cp = *s;
 
                         if cp != PossibleStates::NOP {
-                            println!("empilha {:?}", cp);
-                            stack.push(cp);
+                            children.push((cp, tree.add_node(cp)));
                         } else {
-                            println!("NOP");
+                            trace!("NOP");
                         }
                     }
+
+                    tree.nodes[node].children = children.iter().map(|c| c.1).collect();
+
+                    for c in children.into_iter().rev() {
+                        trace!("empilha {:?}", c.0);
+                        stack.push(c);
+                    }
                 },
                 None => {
-                    println!("stack {:?}", stack);
-                    return Err(CompilationError::SintaxError(format!("Unexpected state for token at: row {}, col {}", tk.row, tk.col)))
+                    trace!("stack {:?}", stack);
+                    return Err(unexpected_token(&tree, &stack, hm, i, target, suggest));
                 },
             }
         }
     }
-    
-    println!("valid!!!");
+
+    // so aceita se a pilha chegou ao $ e o END foi consumido
+    if !stack.is_empty() {
+        let (row, col) = tokens.last().map_or((0, 0), |tk| (tk.row, tk.col));
+        return Err(syntax_error(ErrorCode::UnexpectedEof, "unexpected end of file", row, col, 1));
+    }
+
+    trace!("valid!!!");
+    Ok(tree)
+}
+
+// um '(' nunca fechado: tenta o ')' no fim de cada linha a partir da que abriu e fica com a
+// ultima que deixa o arquivo valido, ja que "(1,)" tambem e valido e o fechamento costuma ficar mais embaixo
+fn repair_unclosed(error: CompilationError, source: &str, target: Version) -> CompilationError {
+    let diagnostic = match error {
+        CompilationError::SintaxError(diagnostic) if diagnostic.code == ErrorCode::UnclosedBracket => *diagnostic,
+        error => return error
+    };
+
+    let lines: Vec<&str> = source.split_inclusive('\n').collect();
+    let (row, col) = (diagnostic.primary.row, diagnostic.primary.col);
+    let closer = match lines[row].chars().nth(col) {
+        Some('(') => ')',
+        Some('[') => ']',
+        _ => '}'
+    };
+
+    let mut found = Vec::new();
+
+    // cada tentativa refaz o lexer e o parser, entao so olha algumas linhas
+    for r in row..lines.len().min(row + 20) {
+        let content = lines[r].trim_end();
+
+        if content.is_empty() {
+            continue;
+        }
+
+        let mut fixed: String = lines[..r].concat();
+        fixed.push_str(content);
+        fixed.push(closer);
+        fixed.push_str(&lines[r][content.len()..]);
+        fixed.push_str(&lines[r + 1..].concat());
+
+        if generate_tokens(&fixed, target).is_ok_and(|tokens| accepted(&tokens, target)) {
+            found.push((r, content.chars().count()));
+        }
+    }
+
+    // sugere a ultima linha que serve, mas se outra tambem servir o fechamento pode estar no lugar errado
+    let applicability = match found.len() {
+        1 => Applicability::MachineApplicable,
+        _ => Applicability::MaybeIncorrect
+    };
+
+    match found.last() {
+        Some(&(r, end)) => {
+            let message = format!("insert '{}' at end of line {}", closer, r + 1);
+            let suggestion = Suggestion {
+                message,
+                row: r,
+                col: end,
+                len: 0,
+                replacement: closer.to_string(),
+                applicability
+            };
+            CompilationError::SintaxError(Box::new(diagnostic.with_suggestion(suggestion)))
+        },
+        None => CompilationError::SintaxError(Box::new(diagnostic))
+    }
+}
+
+fn dump_tree(module: &[ast::Stmt], filename: &str) -> std::io::Result<()> {
+    let mut out = BufWriter::new(File::create(filename)?);
+
+    // uma linha por statement: o {:#?} cresce com o quadrado da profundidade
+    for stmt in module.iter() {
+        writeln!(out, "{:?}", stmt)?;
+    }
+
     Ok(())
 }
 
-fn run(src_file: &str, out_dir: &str) -> std::io::Result<()> {
-    let tokens = generate_tokens(src_file)?;
+fn run(src_file: &str, source: &str, out_dir: &str, target: Version, verbose: bool) -> std::io::Result<Vec<Diagnostic>> {
+    // TODO anotar o processo melhor
+    // TODO `log` seria uma boa ideia
+    if verbose {
+        println!("Tokenazing: \"{}\"", src_file);
+    }
+
+    let tokens = match generate_tokens(source, target).map_err(|e| repair_unclosed(e, source, target)) {
+        Ok(tokens) => tokens,
+        Err(error) => return Ok(diagnostics_of(error))
+    };
 
     let mut filename = out_dir.to_owned();
     filename.push_str("/out.lex");
     dump_tokens(&tokens, &filename)?;
-    let res = parse(&tokens);
+    let res = parse(&tokens, target, verbose, true)
+        .and_then(|tree| ast::lower(&tree))
+        .and_then(|module| version::check(&module, target).map(|_| module));
+
+    match res {
+        Ok(module) => {
+            let mut filename = out_dir.to_owned();
+            filename.push_str("/out.ast");
+            dump_tree(&module, &filename)?;
+            Ok(Vec::new())
+        },
+        Err(error) => Ok(diagnostics_of(error))
+    }
+}
+
+fn diagnostics_of(error: CompilationError) -> Vec<Diagnostic> {
+    let CompilationError::SintaxError(diagnostic) = error;
+    vec![*diagnostic]
+}
+
+fn analyze(source: &str, target: Version) -> Result<Vec<ast::Stmt>, CompilationError> {
+    let tokens = generate_tokens(source, target).map_err(|e| repair_unclosed(e, source, target))?;
+    parse(&tokens, target, false, true).and_then(|tree| ast::lower(&tree))
+}
+
+// false quando o arquivo tem erros e nao ha versao a mostrar
+fn min_version(src_file: &str, format: diagnostics::Format, colour: bool) -> std::io::Result<bool> {
+    let source = std::fs::read_to_string(src_file)?;
+    let oldest = Version::parse(version::TARGETS[0]).unwrap();
+    let newest = Version::parse(version::TARGETS[version::TARGETS.len() - 1]).unwrap();
+
+    // se nem como python 2 o arquivo e valido, parte do 3.0
+    let (module, baseline, python3_only) = match analyze(&source, oldest) {
+        Ok(module) => (module, oldest, None),
+        Err(CompilationError::SintaxError(error)) => match analyze(&source, newest) {
+            Ok(module) => (module, Version(3, 0), Some(error)),
+            Err(error) => {
+                diagnostics::emit(&diagnostics_of(error), format, src_file, &source, colour);
+                return Ok(false);
+            }
+        }
+    };
+    let (minimum, bumps) = version::minimum(&module, baseline);
+
+    println!("Minimum Python version: {}", minimum);
 
-    if let Err(CompilationError::SintaxError(error)) = res {
-        println!("Syntax error: {}", error);
+    if let Some(error) = python3_only {
+        println!("  {: <5} not valid Python 2: {} ({}:{})", baseline, error.message, src_file, error.location());
     }
 
-    Ok(())
+    for f in bumps {
+        println!("  {: <5} {} ({}:{}:{})", f.since, f.name, src_file, f.row + 1, f.col + 1);
+    }
+
+    Ok(true)
+}
+
+fn explain(id: Option<&str>) {
+    let id = match id {
+        Some(id) => id,
+        None => {
+            for code in diagnostics::ERROR_CODES.iter() {
+                println!("{}  {}", code.id(), code.summary());
+            }
+
+            return;
+        }
+    };
+
+    match ErrorCode::from_id(id) {
+        Some(code) => print!("{}", diagnostics::explain(code)),
+        None => {
+            eprintln!("error: unknown error code '{}'", id);
+            std::process::exit(1);
+        }
+    }
 }
 
 fn main() {
@@ -1513,6 +2250,7 @@ fn main() {
         .version("0.1")
         .author("Julio De Bastiani <julioc.debastiani@gmail.com>")
         .about("simple python parser")
+        .setting(AppSettings::SubcommandsNegateReqs)
         .arg(Arg::with_name("INPUT")
             .help("input file")
             .required(true)
@@ -1523,10 +2261,80 @@ fn main() {
             .value_name("OUTDIR")
             .takes_value(true)
             .help("output directory"))
+        .arg(Arg::with_name("target")
+            .short("t")
+            .long("target")
+            .value_name("VERSION")
+            .takes_value(true)
+            .possible_values(&version::TARGETS)
+            .default_value(version::TARGETS[version::TARGETS.len() - 1])
+            .help("python version the source is checked against"))
+        .arg(Arg::with_name("color")
+            .long("color")
+            .value_name("WHEN")
+            .takes_value(true)
+            .global(true)
+            .possible_values(&["auto", "always", "never"])
+            .default_value("auto")
+            .help("colors the error messages"))
+        .arg(Arg::with_name("error_format")
+            .long("error-format")
+            .value_name("FORMAT")
+            .takes_value(true)
+            .global(true)
+            .possible_values(&["human", "json", "sarif"])
+            .default_value("human")
+            .help("format of the error messages, json and sarif are written to stdout"))
+        .subcommand(SubCommand::with_name("min-version")
+            .about("reports the oldest python version able to run the file")
+            .arg(Arg::with_name("INPUT")
+                .help("input file")
+                .required(true)
+                .index(1)))
+        .subcommand(SubCommand::with_name("explain")
+            .about("explains an error code, or lists all codes")
+            .arg(Arg::with_name("CODE")
+                .help("error code, such as PP0005")
+                .index(1)))
         .get_matches();
 
+    let colour = match matches.value_of("color").unwrap() {
+        "always" => true,
+        "never" => false,
+        _ => std::io::stderr().is_terminal()
+    };
+
+    let format = match matches.value_of("error_format").unwrap() {
+        "json" => diagnostics::Format::Json,
+        "sarif" => diagnostics::Format::Sarif,
+        _ => diagnostics::Format::Human
+    };
+
+    if let Some(matches) = matches.subcommand_matches("explain") {
+        explain(matches.value_of("CODE"));
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("min-version") {
+        if !min_version(matches.value_of("INPUT").unwrap(), format, colour).unwrap() {
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
     let src_file = matches.value_of("INPUT").unwrap();
-    let out_dir = matches.value_of("OUTDIR").unwrap_or("out");
+    let out_dir = matches.value_of("output_dir").unwrap_or("out");
+    let target = Version::parse(matches.value_of("target").unwrap()).unwrap();
     
-    run(src_file, out_dir).unwrap();
+    let source = std::fs::read_to_string(src_file).unwrap();
+    // o passo a passo do parser sujaria o JSON na saida padrao
+    let diagnostics = run(src_file, &source, out_dir, target, format == diagnostics::Format::Human).unwrap();
+
+    diagnostics::emit(&diagnostics, format, src_file, &source, colour);
+
+    // para o CI falhar quando houver erro
+    if !diagnostics.is_empty() {
+        std::process::exit(1);
+    }
 }
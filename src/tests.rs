@@ -0,0 +1,640 @@
+// Casos de ponta a ponta: fonte -> tokens -> arvore -> AST, mais os erros de cada etapa.
+// As expressoes sao comparadas numa forma compacta, (Sub (Sub a b) c), sem as posicoes.
+
+use super::*;
+use diagnostics::ERROR_CODES;
+use ast::{Comprehension, Constant, Expr, ExprKind, PatternKind, Stmt, StmtKind, TypeParam};
+
+fn check(source: &str, target: &str) -> Result<Vec<Stmt>, Box<Diagnostic>> {
+    let target = Version::parse(target).unwrap();
+
+    analyze(source, target)
+        .and_then(|module| version::check(&module, target).map(|_| module))
+        .map_err(|CompilationError::SintaxError(diagnostic)| diagnostic)
+}
+
+fn lower(source: &str) -> Vec<Stmt> {
+    check(source, "3.12").unwrap_or_else(|d| panic!("{}: {}", d.location(), d.message))
+}
+
+fn error(source: &str, target: &str) -> Diagnostic {
+    match check(source, target) {
+        Ok(module) => panic!("expected an error, got {:?}", module),
+        Err(diagnostic) => *diagnostic
+    }
+}
+
+fn assert_error(source: &str, code: ErrorCode, message: &str) {
+    let diagnostic = error(source, "3.12");
+    assert_eq!((diagnostic.code, diagnostic.message.as_str()), (code, message), "{:?}", source);
+}
+
+// o unico statement do fonte, uma expressao ou o valor de uma atribuicao
+fn expression(source: &str) -> Expr {
+    let mut module = lower(source);
+    assert_eq!(module.len(), 1, "{:?}", source);
+
+    match module.pop().unwrap().kind {
+        StmtKind::Expr(expr) | StmtKind::Assign { value: expr, .. } => expr,
+        kind => panic!("expected an expression, got {:?}", kind)
+    }
+}
+
+fn sexp(expr: &Expr) -> String {
+    let list = |head: &str, items: Vec<String>| match items.len() {
+        0 => format!("({})", head),
+        _ => format!("({} {})", head, items.join(" "))
+    };
+    let all = |exprs: &[Expr]| exprs.iter().map(sexp).collect::<Vec<_>>();
+    let optional = |expr: &Option<Box<Expr>>| expr.as_ref().map_or("_".to_string(), |e| sexp(e));
+
+    match &expr.kind {
+        ExprKind::Name(name) => name.clone(),
+        ExprKind::Constant(Constant::Int(s)) | ExprKind::Constant(Constant::Float(s)) | ExprKind::Constant(Constant::Imaginary(s)) | ExprKind::Constant(Constant::Str(s)) => s.clone(),
+        ExprKind::Constant(Constant::Bool(b)) => if *b { "True" } else { "False" }.to_string(),
+        ExprKind::Constant(Constant::None) => "None".to_string(),
+        ExprKind::Constant(Constant::Ellipsis) => "...".to_string(),
+        ExprKind::JoinedStr(s) => s.clone(),
+        ExprKind::BoolOp { op, values } => list(&format!("{:?}", op), all(values)),
+        ExprKind::BinOp { left, op, right } => list(&format!("{:?}", op), vec![sexp(left), sexp(right)]),
+        ExprKind::UnaryOp { op, operand } => list(&format!("{:?}", op), vec![sexp(operand)]),
+        ExprKind::Compare { left, ops, comparators } => {
+            let mut items = vec![sexp(left)];
+
+            for (op, comparator) in ops.iter().zip(comparators) {
+                items.push(format!("{:?}", op));
+                items.push(sexp(comparator));
+            }
+
+            list("Compare", items)
+        },
+        ExprKind::NamedExpr { target, value } => list(":=", vec![sexp(target), sexp(value)]),
+        ExprKind::Lambda { body, .. } => list("lambda", vec![sexp(body)]),
+        ExprKind::IfExp { test, body, orelse } => list("if", vec![sexp(test), sexp(body), sexp(orelse)]),
+        ExprKind::Await(value) => list("await", vec![sexp(value)]),
+        ExprKind::Yield(value) => list("yield", value.iter().map(|v| sexp(v)).collect()),
+        ExprKind::YieldFrom(value) => list("yield-from", vec![sexp(value)]),
+        ExprKind::Call { func, args, keywords } => {
+            let mut items = vec![sexp(func)];
+            items.extend(all(args));
+
+            for keyword in keywords {
+                let arg = keyword.arg.as_deref().unwrap_or("**");
+                items.push(format!("{}={}", arg, sexp(&keyword.value)));
+            }
+
+            list("call", items)
+        },
+        ExprKind::Attribute { value, attr } => list(".", vec![sexp(value), attr.clone()]),
+        ExprKind::Subscript { value, slice } => list("[]", vec![sexp(value), sexp(slice)]),
+        ExprKind::Slice { lower, upper, step } => list(":", vec![optional(lower), optional(upper), optional(step)]),
+        ExprKind::Starred(value) => list("*", vec![sexp(value)]),
+        ExprKind::List(elts) => list("list", all(elts)),
+        ExprKind::Tuple(elts) => list("tuple", all(elts)),
+        ExprKind::Set(elts) => list("set", all(elts)),
+        ExprKind::Dict { keys, values } => {
+            let items = keys.iter().zip(values)
+                .map(|(k, v)| format!("{}:{}", k.as_ref().map_or("**".to_string(), sexp), sexp(v)))
+                .collect();
+
+            list("dict", items)
+        },
+        ExprKind::ListComp { elt, generators } => list("listcomp", comprehension(vec![sexp(elt)], generators)),
+        ExprKind::SetComp { elt, generators } => list("setcomp", comprehension(vec![sexp(elt)], generators)),
+        ExprKind::GeneratorExp { elt, generators } => list("genexp", comprehension(vec![sexp(elt)], generators)),
+        ExprKind::DictComp { key, value, generators } => list("dictcomp", comprehension(vec![sexp(key), sexp(value)], generators))
+    }
+}
+
+fn comprehension(mut items: Vec<String>, generators: &[Comprehension]) -> Vec<String> {
+    for generator in generators {
+        let head = if generator.is_async { "async-for" } else { "for" };
+        let mut clause = vec![sexp(&generator.target), sexp(&generator.iter)];
+        clause.extend(generator.ifs.iter().map(|test| format!("(if {})", sexp(test))));
+        items.push(format!("({} {})", head, clause.join(" ")));
+    }
+
+    items
+}
+
+fn assert_sexp(source: &str, expected: &str) {
+    assert_eq!(sexp(&expression(source)), expected, "{:?}", source);
+}
+
+// a recursao do parser e da lowering foi medida com a pilha da thread principal (8MB), maior
+// que a das threads de teste
+fn with_main_stack<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+    std::thread::Builder::new()
+        .stack_size(8 << 20)
+        .spawn(f)
+        .unwrap()
+        .join()
+        .unwrap()
+}
+
+#[test]
+fn trailers() {
+    assert_sexp("a.b[1](x).c\n", "(. (call ([] (. a b) 1) x) c)");
+    assert_sexp("a[1:2, ::3]\n", "([] a (tuple (: 1 2 _) (: _ _ 3)))");
+    assert_sexp("a[:]\n", "([] a (: _ _ _))");
+    assert_sexp("f(x, *y, k=1, **z)\n", "(call f x (* y) k=1 **=z)");
+}
+
+#[test]
+fn parameters() {
+    let module = lower("def f(a, /, b=1, *args, c, d=2, **kw):\n    x = 1\n");
+
+    match &module[0].kind {
+        StmtKind::FunctionDef { args, .. } => {
+            assert_eq!(args.posonlyargs.len(), 1);
+            assert_eq!(args.args[0].name, "b");
+            assert!(args.args[0].default.is_some());
+            assert_eq!(args.vararg.as_ref().unwrap().name, "args");
+            assert_eq!(args.kwonlyargs.iter().map(|a| a.name.as_str()).collect::<Vec<_>>(), ["c", "d"]);
+            assert_eq!(args.kwarg.as_ref().unwrap().name, "kw");
+        },
+        kind => panic!("{:?}", kind)
+    }
+
+    lower("def f():\n    x = 1\n");
+    assert_error("def f(a, a):\n    x = 1\n", ErrorCode::DuplicateArgument, "duplicate argument 'a' in function definition");
+    assert_error("def f(a=1, b):\n    x = 1\n", ErrorCode::InvalidArguments, "non-default argument follows default argument");
+}
+
+#[test]
+fn lambdas_and_conditionals() {
+    assert_sexp("x = a if b else lambda y: y + 1\n", "(if b a (lambda (Add y 1)))");
+    assert_sexp("x = lambda: a if b else c\n", "(lambda (if b a c))");
+}
+
+#[test]
+fn comprehensions() {
+    assert_sexp("x = [x for x in y if x if z]\n", "(listcomp x (for x y (if x) (if z)))");
+    assert_sexp("x = {k: v for k, v in d}\n", "(dictcomp k v (for (tuple k v) d))");
+    assert_sexp("x = {x for x in y for z in x}\n", "(setcomp x (for x y) (for z x))");
+    assert_sexp("f(x for x in y)\n", "(call f (genexp x (for x y)))");
+    assert_error("x = [*x for x in y]\n", ErrorCode::InvalidUnpacking, "iterable unpacking cannot be used in comprehension");
+
+    // o if de uma compreensao e uma disjuncao, lambda e if/else so entre parenteses
+    assert_sexp("x = [x for x in y if (lambda: 1)]\n", "(listcomp x (for x y (if (lambda 1))))");
+    assert_sexp("x = [x for x in y if (a if b else c)]\n", "(listcomp x (for x y (if (if b a c))))");
+    assert_error("x = [x for x in y if lambda: 1]\n", ErrorCode::UnexpectedToken, "expected expression or 'not' after 'if', found keyword 'lambda'");
+    assert_error("x = [x for x in y if a if b else c]\n", ErrorCode::UnexpectedToken, "expected one of ']', 'async', 'for', 'if' after expression, found keyword 'else'");
+}
+
+#[test]
+fn decorators() {
+    let module = lower("@a.b(1)\n@c\ndef f():\n    x = 1\n@d\nclass C:\n    x = 1\n");
+
+    match (&module[0].kind, &module[1].kind) {
+        (StmtKind::FunctionDef { decorator_list: f, .. }, StmtKind::ClassDef { decorator_list: c, .. }) => {
+            assert_eq!(f.iter().map(sexp).collect::<Vec<_>>(), ["(call (. a b) 1)", "c"]);
+            assert_eq!(c.iter().map(sexp).collect::<Vec<_>>(), ["d"]);
+        },
+        kinds => panic!("{:?}", kinds)
+    }
+
+    match &lower("@x := y\ndef f():\n    pass\n")[0].kind {
+        StmtKind::FunctionDef { decorator_list, .. } => assert_eq!(sexp(&decorator_list[0]), "(:= x y)"),
+        kind => panic!("{:?}", kind)
+    }
+}
+
+#[test]
+fn simple_statements() {
+    let module = lower("pass\ndel a, b[0]\nglobal x, y\nassert x, 'message'\n");
+
+    assert!(matches!(module[0].kind, StmtKind::Pass));
+    assert!(matches!(&module[1].kind, StmtKind::Delete(targets) if targets.len() == 2));
+    assert!(matches!(&module[2].kind, StmtKind::Global(names) if names == &["x", "y"]));
+    assert!(matches!(&module[3].kind, StmtKind::Assert { msg: Some(_), .. }));
+
+    lower("def f():\n    x = 1\n    def g():\n        nonlocal x\n        x = 2\n");
+    lower("def f():\n    def g():\n        nonlocal x\n    x = 1\n");
+    assert_error("nonlocal x\n", ErrorCode::InvalidDeclaration, "nonlocal declaration not allowed at module level");
+    assert_error("del f()\n", ErrorCode::InvalidTarget, "cannot delete function call");
+}
+
+#[test]
+fn declaration_order() {
+    assert_error("def f():\n    x = 1\n    global x\n", ErrorCode::InvalidDeclaration, "name 'x' is assigned to before global declaration");
+    assert_error("def f():\n    print(x)\n    global x\n", ErrorCode::InvalidDeclaration, "name 'x' is used prior to global declaration");
+    assert_error("def f():\n    def g():\n        nonlocal x\n", ErrorCode::InvalidDeclaration, "no binding for nonlocal 'x' found");
+    assert_error("def f():\n    global x\n    def g():\n        nonlocal x\n", ErrorCode::InvalidDeclaration, "no binding for nonlocal 'x' found");
+    assert_error("def f(x):\n    global x\n", ErrorCode::InvalidDeclaration, "name 'x' is parameter and global");
+}
+
+#[test]
+fn generators_and_coroutines() {
+    let module = lower("def g():\n    yield 1\n    yield from h()\n    x = yield\n");
+    assert!(matches!(module[0].kind, StmtKind::FunctionDef { is_generator: true, .. }));
+
+    let module = lower("async def f(x):\n    await x\n    async for i in x:\n        pass\n    async with x as y:\n        pass\n    return [i async for i in x]\n");
+
+    match &module[0].kind {
+        StmtKind::FunctionDef { is_async: true, body, .. } => {
+            assert!(matches!(&body[0].kind, StmtKind::Expr(e) if sexp(e) == "(await x)"));
+            assert!(matches!(body[1].kind, StmtKind::For { is_async: true, .. }));
+            assert!(matches!(body[2].kind, StmtKind::With { is_async: true, .. }));
+            assert!(matches!(&body[3].kind, StmtKind::Return(Some(e)) if sexp(e) == "(listcomp i (async-for i x))"));
+        },
+        kind => panic!("{:?}", kind)
+    }
+
+    // a expressao geradora vira um gerador async, pode ficar fora de uma funcao async
+    lower("def f(x):\n    return (i async for i in x)\n");
+
+    assert_error("def f():\n    await x\n", ErrorCode::InvalidContext, "'await' outside async function");
+    assert_error("yield x\n", ErrorCode::InvalidContext, "'yield' outside function");
+    assert_error("def f(x):\n    return [i async for i in x]\n", ErrorCode::InvalidContext, "asynchronous comprehension outside of an asynchronous function");
+    assert_error("async def f():\n    yield 1\n    return 2\n", ErrorCode::InvalidContext, "'return' with value in async generator");
+
+    // o elemento, as condicoes e os iteraveis internos rodam no escopo da compreensao
+    lower("def g(x):\n    return [i for i in (yield x)]\n");
+    lower("def g(x):\n    return [lambda: (yield) for i in x]\n");
+    assert_error("def g(x):\n    return [(yield i) for i in x]\n", ErrorCode::InvalidContext, "'yield' inside list comprehension");
+    assert_error("def g(x):\n    return {i for i in x if (yield)}\n", ErrorCode::InvalidContext, "'yield' inside set comprehension");
+    assert_error("def g(x):\n    return {i: (yield from i) for i in x}\n", ErrorCode::InvalidContext, "'yield' inside dict comprehension");
+    assert_error("def g(x):\n    return list(i for j in x for i in (yield j))\n", ErrorCode::InvalidContext, "'yield' inside generator expression");
+
+    // antes do 3.7 async e await so sao reservados dentro de um async def
+    let module = check("await = 1\nasync = await\nasync def f(x):\n    await x\n    async for i in x:\n        pass\ndef g():\n    await = 2\n", "3.6").unwrap();
+    assert!(matches!(&module[2].kind, StmtKind::FunctionDef { is_async: true, body, .. } if matches!(&body[0].kind, StmtKind::Expr(e) if sexp(e) == "(await x)")));
+    assert_eq!(error("await = 1\n", "3.8").code, ErrorCode::UnexpectedToken);
+}
+
+#[test]
+fn precedence() {
+    assert_sexp("x = a or b and not c == d | e ^ f & g << h + i * -j ** k\n", "(Or a (And b (Not (Compare c Eq (BitOr d (BitXor e (BitAnd f (LShift g (Add h (Mult i (USub (Pow j k))))))))))))");
+    assert_sexp("x = -a ** -b\n", "(USub (Pow a (USub b)))");
+
+    match &lower("async def f():\n    return await a ** b\n")[0].kind {
+        StmtKind::FunctionDef { body, .. } => assert!(matches!(&body[0].kind, StmtKind::Return(Some(e)) if sexp(e) == "(Pow (await a) b)")),
+        kind => panic!("{:?}", kind)
+    }
+}
+
+#[test]
+fn associativity() {
+    assert_sexp("x = a - b - c\n", "(Sub (Sub a b) c)");
+    assert_sexp("x = a / b * c // d % e @ f\n", "(MatMult (Mod (FloorDiv (Mult (Div a b) c) d) e) f)");
+    assert_sexp("x = a ** b ** c\n", "(Pow a (Pow b c))");
+    assert_sexp("x = a < b <= c is not d not in e\n", "(Compare a Lt b LtE c IsNot d NotIn e)");
+}
+
+#[test]
+fn constants() {
+    assert_sexp("x = (True, False, None, ...)\n", "(tuple True False None ...)");
+    assert_error("del True\n", ErrorCode::InvalidTarget, "cannot delete True");
+
+    assert_sexp("x = 2j + 1.5J * .5j\n", "(Add 2j (Mult 1.5J .5j))");
+    assert_error("x = 2jx\n", ErrorCode::InvalidLiteral, "invalid decimal literal");
+}
+
+#[test]
+fn for_targets() {
+    let module = lower("for a, (b, *c) in x, y:\n    pass\nfor x.y[0] in z:\n    pass\n");
+
+    match (&module[0].kind, &module[1].kind) {
+        (StmtKind::For { target: first, iter, .. }, StmtKind::For { target: second, .. }) => {
+            assert_eq!(sexp(first), "(tuple a (tuple b (* c)))");
+            assert_eq!(sexp(iter), "(tuple x y)");
+            assert_eq!(sexp(second), "([] (. x y) 0)");
+        },
+        kinds => panic!("{:?}", kinds)
+    }
+
+    match &lower("for x in *a, *b:\n    pass\n")[0].kind {
+        StmtKind::For { iter, .. } => assert_eq!(sexp(iter), "(tuple (* a) (* b))"),
+        kind => panic!("{:?}", kind)
+    }
+
+    assert_error("for x in *a:\n    pass\n", ErrorCode::InvalidUnpacking, "can't use starred expression here");
+}
+
+#[test]
+fn semicolons_and_one_line_bodies() {
+    assert_eq!(lower("x = 1; y = 2;\n").len(), 2);
+
+    match &lower("if x: pass; y = 1\nelse: z = 2\n")[0].kind {
+        StmtKind::If { body, orelse, .. } => assert_eq!((body.len(), orelse.len()), (2, 1)),
+        kind => panic!("{:?}", kind)
+    }
+}
+
+#[test]
+fn unpacking_and_chained_assignment() {
+    match &lower("a, *b = c = d\n")[0].kind {
+        StmtKind::Assign { targets, value } => {
+            assert_eq!(targets.iter().map(sexp).collect::<Vec<_>>(), ["(tuple a (* b))", "c"]);
+            assert_eq!(sexp(value), "d");
+        },
+        kind => panic!("{:?}", kind)
+    }
+
+    assert_error("*a, *b = c\n", ErrorCode::InvalidTarget, "multiple starred expressions in assignment");
+    assert_error("*a = b\n", ErrorCode::InvalidTarget, "starred assignment target must be in a list or tuple");
+    assert_error("x = *a\n", ErrorCode::InvalidUnpacking, "can't use starred expression here");
+    assert_error("True = 1\n", ErrorCode::InvalidTarget, "cannot assign to True");
+}
+
+#[test]
+fn augmented_and_annotated_assignment() {
+    for op in ["+", "-", "*", "/", "//", "%", "**", "@", "<<", ">>", "&", "^", "|"].iter() {
+        assert!(matches!(lower(&format!("x {}= 1\n", op))[0].kind, StmtKind::AugAssign { .. }), "{}", op);
+    }
+
+    match &lower("x: int = 1\n")[0].kind {
+        StmtKind::AnnAssign { target, annotation, value: Some(_), simple: true } => {
+            assert_eq!((sexp(target), sexp(annotation)), ("x".to_string(), "int".to_string()));
+        },
+        kind => panic!("{:?}", kind)
+    }
+
+    assert_error("a, b += 1\n", ErrorCode::InvalidTarget, "'tuple' is an illegal expression for augmented assignment");
+    assert_error("a, b: int\n", ErrorCode::InvalidTarget, "only single target (not tuple) can be annotated");
+}
+
+#[test]
+fn annotations() {
+    match &lower("def f(a: int, *b: str) -> list[int]:\n    pass\n")[0].kind {
+        StmtKind::FunctionDef { args, returns: Some(returns), .. } => {
+            assert_eq!(sexp(args.args[0].annotation.as_ref().unwrap()), "int");
+            assert_eq!(sexp(args.vararg.as_ref().unwrap().annotation.as_ref().unwrap()), "str");
+            assert_eq!(sexp(returns), "([] list int)");
+        },
+        kind => panic!("{:?}", kind)
+    }
+}
+
+#[test]
+fn pattern_matching() {
+    let module = lower("match p:\n    case [1, *rest] if rest:\n        pass\n    case Point(x=0) | {'k': _}:\n        pass\n    case _:\n        pass\n");
+
+    match &module[0].kind {
+        StmtKind::Match { subject, cases } => {
+            assert_eq!(sexp(subject), "p");
+            assert!(matches!(&cases[0].pattern.kind, PatternKind::Sequence(items) if items.len() == 2));
+            assert!(cases[0].guard.is_some());
+            assert!(matches!(&cases[1].pattern.kind, PatternKind::Or(alternatives) if alternatives.len() == 2));
+            assert!(matches!(cases[2].pattern.kind, PatternKind::As { pattern: None, name: None }));
+        },
+        kind => panic!("{:?}", kind)
+    }
+
+    assert_error("match p:\n    case (x, x):\n        pass\n", ErrorCode::InvalidPattern, "multiple assignments to name 'x' in pattern");
+    assert_error("match p:\n    case x:\n        pass\n    case 1:\n        pass\n", ErrorCode::InvalidPattern, "name capture 'x' makes remaining patterns unreachable");
+
+    // fora do comeco de um match, case e type continuam sendo nomes
+    let diagnostic = error("match x:\n    case:\n        pass\n", "3.12");
+    assert!(diagnostic.message.ends_with("after 'case', found ':'"), "{}", diagnostic.message);
+
+    match &lower("match p:\n    case -1 + 2j | 1.5 - 0.5J | 3j:\n        pass\n")[0].kind {
+        StmtKind::Match { cases, .. } => match &cases[0].pattern.kind {
+            PatternKind::Or(alternatives) => {
+                let values: Vec<String> = alternatives.iter().map(|p| match &p.kind {
+                    PatternKind::Value(value) => sexp(value),
+                    kind => panic!("{:?}", kind)
+                }).collect();
+
+                assert_eq!(values, ["(Add (USub 1) 2j)", "(Sub 1.5 0.5J)", "3j"]);
+            },
+            kind => panic!("{:?}", kind)
+        },
+        kind => panic!("{:?}", kind)
+    }
+
+    assert_error("match p:\n    case 1j + 2j:\n        pass\n", ErrorCode::InvalidPattern, "real number required in complex literal");
+    assert_error("match p:\n    case 1 + 2:\n        pass\n", ErrorCode::InvalidPattern, "imaginary number required in complex literal");
+    assert_error("match p:\n    case {1: _, 1: _}:\n        pass\n", ErrorCode::InvalidPattern, "mapping pattern checks duplicate key (1)");
+    assert_error("match p:\n    case {'k': _, \"k\": _}:\n        pass\n", ErrorCode::InvalidPattern, "mapping pattern checks duplicate key ('k')");
+    lower("match p:\n    case {'k': _, b'k': _, a.b: _, a.b: _}:\n        pass\n");
+}
+
+#[test]
+fn soft_keywords_and_type_aliases() {
+    let module = lower("match = 1\ncase = match\ntype = 2\nprint(type)\n");
+    assert!(module.iter().all(|stmt| matches!(stmt.kind, StmtKind::Assign { .. } | StmtKind::Expr(_))));
+
+    match &lower("type Y[T: int, *Ts, **P] = list[T]\n")[0].kind {
+        StmtKind::TypeAlias { name, type_params, value } => {
+            assert_eq!((sexp(name), sexp(value)), ("Y".to_string(), "([] list T)".to_string()));
+            assert!(matches!(&type_params[0], TypeParam::TypeVar { name, bound: Some(_) } if name == "T"));
+            assert!(matches!(&type_params[1], TypeParam::TypeVarTuple(name) if name == "Ts"));
+            assert!(matches!(&type_params[2], TypeParam::ParamSpec(name) if name == "P"));
+        },
+        kind => panic!("{:?}", kind)
+    }
+
+    assert_error("type A[T, T] = int\n", ErrorCode::DuplicateArgument, "duplicate type parameter 'T'");
+
+    // statements que nao comecam a linha
+    let module = lower("x = 1; type X = int\nif x: type X = int\nclass A: type X = int\nif x: y = 1; type X = int\n");
+    assert!(matches!(module[1].kind, StmtKind::TypeAlias { .. }));
+    assert!(matches!(&module[2].kind, StmtKind::If { body, .. } if matches!(body[0].kind, StmtKind::TypeAlias { .. })));
+    assert!(matches!(&module[3].kind, StmtKind::ClassDef { body, .. } if matches!(body[0].kind, StmtKind::TypeAlias { .. })));
+    assert!(matches!(&module[4].kind, StmtKind::If { body, .. } if matches!(body[1].kind, StmtKind::TypeAlias { .. })));
+    assert_eq!(lower("if x: match = 1; case = 2\nx = {'a': 1}; type = 3\n").len(), 3);
+}
+
+#[test]
+fn assignment_expressions() {
+    match &lower("if (n := len(a)) > 1:\n    pass\n")[0].kind {
+        StmtKind::If { test, .. } => assert_eq!(sexp(test), "(Compare (:= n (call len a)) Gt 1)"),
+        kind => panic!("{:?}", kind)
+    }
+
+    assert_error("(a.b := 1)\n", ErrorCode::InvalidTarget, "cannot use assignment expressions with attribute");
+
+    // o := de uma compreensao atribui no escopo de fora
+    lower("x = [y := i for i in x]\ndef f():\n    class A:\n        pass\n    return [(y := 1) for i in x]\n");
+    lower("class A:\n    f = lambda: [(y := 1) for i in x]\n");
+    assert_error("x = [i := 0 for i in x]\n", ErrorCode::InvalidTarget, "assignment expression cannot rebind comprehension iteration variable 'i'");
+    assert_error("x = [(j := 0) for i in range(5) for j in x]\n", ErrorCode::InvalidTarget, "assignment expression cannot rebind comprehension iteration variable 'j'");
+    assert_error("x = {k: [(v := 1) for a in b] for k, v in d}\n", ErrorCode::InvalidTarget, "assignment expression cannot rebind comprehension iteration variable 'v'");
+    assert_error("x = [x for x in (y := [1])]\n", ErrorCode::InvalidContext, "assignment expression cannot be used in a comprehension iterable expression");
+    assert_error("x = [x for i in a for x in (y := i)]\n", ErrorCode::InvalidContext, "assignment expression cannot be used in a comprehension iterable expression");
+    assert_error("class A:\n    [(y := 1) for i in x]\n", ErrorCode::InvalidContext, "assignment expression within a comprehension cannot be used in a class body");
+}
+
+#[test]
+fn targets() {
+    assert!(matches!(check("print 'x'\nexec code in ns\n", "2.7").unwrap()[0].kind, StmtKind::Print { .. }));
+    assert!(check("print 'x'\n", "3.12").is_err());
+    assert!(check("nonlocal = True\n", "2.7").is_ok());
+    assert_eq!(error("x = f'{y}'\n", "2.7").message, "f-strings require Python 3.6 or greater (target is 2.7)");
+}
+
+#[test]
+fn minimum_version() {
+    let module = lower("x = f'{y}'\nif (n := 1):\n    pass\nz = f'{n}'\n");
+    let (minimum, bumps) = version::minimum(&module, Version(3, 0));
+
+    assert_eq!(minimum, Version(3, 8));
+    assert_eq!(bumps.iter().map(|f| (f.name, f.since)).collect::<Vec<_>>(), [("f-strings", Version(3, 6)), ("assignment expressions", Version(3, 8))]);
+
+    let oldest = |source: &str| version::minimum(&lower(source), Version(3, 0)).0;
+
+    assert_eq!(oldest("for x in *a, *b:\n    pass\n"), Version(3, 9));
+    assert_eq!(oldest("for x in (*a, *b):\n    pass\n"), Version(3, 5));
+    assert_eq!(oldest("x: int = 1, 2\n"), Version(3, 8));
+    assert_eq!(oldest("x: int = (1, 2)\n"), Version(3, 6));
+
+    assert_eq!(error("for x in *a, *b:\n    pass\n", "3.8").code, ErrorCode::UnsupportedFeature);
+    assert_eq!(error("x: int = 1, 2\n", "3.6").code, ErrorCode::UnsupportedFeature);
+}
+
+#[test]
+fn end_of_input() {
+    assert!(lower("").is_empty());
+    assert!(lower("# only a comment\n\n").is_empty());
+    assert!(lower("\n   \n").is_empty());
+
+    assert!(error("if x:\n", "3.12").message.starts_with("unexpected end of file"));
+    assert_error("x = 1 +\n", ErrorCode::UnexpectedEof, "unexpected end of file, expected expression after '+'");
+    assert_error("def f():\n    return 1 +", ErrorCode::UnexpectedEof, "unexpected end of file, expected expression after '+'");
+    assert_eq!(error("x = (1,\n", "3.12").message, "'(' was never closed");
+}
+
+#[test]
+fn expected_tokens() {
+    let python3 = error("x = 1; else\n", "3.12").message;
+    assert!(python3.starts_with("expected one of") && python3.ends_with("after ';', found keyword 'else'"), "{}", python3);
+    assert!(python3.contains("'nonlocal'") && !python3.contains("'print'") && !python3.contains("'type'"), "{}", python3);
+
+    let python2 = error("x = 1; else\n", "2.7").message;
+    assert!(python2.contains("'print'") && !python2.contains("'nonlocal'"), "{}", python2);
+
+    assert_eq!(error("match x:\n    1\n", "3.12").message, "expected 'case' after indent, found integer '1'");
+}
+
+#[test]
+fn rendering() {
+    let source = "x = (1]\n";
+    let diagnostic = error(source, "3.12");
+    let rendered = diagnostic.render("a.py", source, false);
+
+    assert_eq!(diagnostic.code, ErrorCode::MismatchedBracket);
+    assert!(rendered.starts_with("error[PP0005]: closing parenthesis ']' does not match opening parenthesis '('\n --> a.py:1:7\n"), "{}", rendered);
+    assert!(rendered.contains("1 | x = (1]\n") && rendered.contains("^"), "{}", rendered);
+
+    // as marcas seguem a largura do texto: tabs continuam tabs e o CJK ocupa duas colunas
+    let source = "if x:\n\ty = '名前' $\n";
+    let rendered = error(source, "3.12").render("a.py", source, false);
+    assert!(rendered.contains("2 | \ty = '名前' $\n  | \t           ^\n"), "{}", rendered);
+}
+
+#[test]
+fn machine_readable_output() {
+    let diagnostics = vec![error("x = (1]\n", "3.12")];
+
+    let json = diagnostics::to_json(&diagnostics, "a.py");
+    assert!(json.starts_with("[{\"code\":\"PP0005\",\"severity\":\"error\""), "{}", json);
+    assert_eq!(diagnostics::to_json(&[], "a.py"), "[]");
+
+    let sarif = diagnostics::to_sarif(&diagnostics, "/tmp/dir with space/á.py");
+    assert!(sarif.contains("\"ruleId\":\"PP0005\""), "{}", sarif);
+    assert!(sarif.contains("\"columnKind\":\"unicodeCodePoints\""), "{}", sarif);
+    assert!(sarif.contains("\"uri\":\"file:///tmp/dir%20with%20space/%C3%A1.py\""), "{}", sarif);
+    assert!(diagnostics::to_sarif(&diagnostics, "src/a b.py").contains("\"uri\":\"src/a%20b.py\""));
+
+    // erros da AST marcam o no inteiro, nao so o primeiro caractere
+    let span = |source: &str| {
+        let primary = error(source, "3.12").primary;
+        (primary.col, primary.len)
+    };
+
+    assert_eq!(span("f() = 1\n"), (0, 3));
+    assert_eq!(span("f(x=1, x=2)\n"), (7, 3));
+    assert_eq!(span("a + b.c[1:2] += 1\n"), (0, 12));
+    assert_eq!(span("match x:\n    case {'a': 1, 'a': 2}:\n        pass\n"), (18, 3));
+
+    let json = diagnostics::to_json(&[error("[a, b()] = 1\n", "3.12")], "a.py");
+    assert!(json.contains("\"span\":{\"start\":{\"line\":1,\"column\":5},\"end\":{\"line\":1,\"column\":8}}"), "{}", json);
+}
+
+#[test]
+fn error_codes() {
+    for (k, code) in ERROR_CODES.iter().enumerate() {
+        assert_eq!(ErrorCode::from_id(&code.id()), Some(*code));
+        assert!(ERROR_CODES[..k].iter().all(|other| other.id() != code.id()));
+    }
+
+    assert_eq!(ErrorCode::MismatchedBracket.id(), "PP0005");
+    assert_eq!(ErrorCode::from_id("pp15"), Some(ErrorCode::InvalidTarget));
+    assert_eq!(ErrorCode::from_id("PP0999"), None);
+
+    // o exemplo de cada explicacao tem que dar o proprio codigo
+    for code in ERROR_CODES.iter() {
+        let (_, example, _) = code.explanation();
+        let target = example.strip_prefix("# with --target ").map_or("3.12", |rest| rest.lines().next().unwrap());
+        let diagnostic = error(&format!("{}\n", example), target);
+
+        assert_eq!(diagnostic.code, *code, "{:?}: {}", example, diagnostic.message);
+    }
+}
+
+#[test]
+fn repairs() {
+    let suggestion = |source: &str| error(source, "3.12").suggestions.pop();
+
+    let colon = suggestion("def f(x)\n    return x\n").unwrap();
+    assert_eq!((colon.message.as_str(), colon.replacement.as_str()), ("insert ':' at end of line 1", ":"));
+    assert_eq!(colon.applicability, Applicability::MachineApplicable);
+
+    // , e : deixam o arquivo valido
+    assert_eq!(suggestion("d = {'a' 1}\n").unwrap().applicability, Applicability::MaybeIncorrect);
+
+    // com a virgula o lambda teria um argumento repetido
+    assert_ne!(suggestion("f = lambda x x: 1\n").unwrap().replacement, ",");
+
+    // fechado na linha 1 ou na 2 (f(1, y = 2)) o arquivo fica valido, a sugestao e a ultima
+    let closer = suggestion("x = f(1,\ny = 2\n").unwrap();
+    assert_eq!((closer.message.as_str(), closer.applicability), ("insert ')' at end of line 2", Applicability::MaybeIncorrect));
+    assert_eq!(suggestion("x = [a,\nb\n").unwrap().applicability, Applicability::MaybeIncorrect);
+
+    let closer = suggestion("x = f(1, 2\n").unwrap();
+    assert_eq!((closer.message.as_str(), closer.applicability), ("insert ')' at end of line 1", Applicability::MachineApplicable));
+
+    // def f(a, /, b=1) so e valido a partir do 3.8
+    let positional = |target: &str| error("def f(a, / b=1):\n    pass\n", target).suggestions.pop().unwrap();
+    assert_eq!(positional("3.12").applicability, Applicability::MachineApplicable);
+    assert_eq!(positional("3.6").applicability, Applicability::MaybeIncorrect);
+}
+
+#[test]
+fn deep_nesting() {
+    with_main_stack(|| {
+        let depth = |n: usize| format!("x = {}1{}\n", "(".repeat(n), ")".repeat(n));
+
+        assert_eq!(lower(&depth(199)).len(), 1);
+        assert_eq!(error(&depth(201), "3.12").message, "too many nested parentheses");
+
+        assert_eq!(lower(&format!("x = {}1\n", "-".repeat(499))).len(), 1);
+        assert_eq!(error(&format!("x = {}1\n", "-".repeat(20000)), "3.12").code, ErrorCode::TooDeeplyNested);
+        assert_eq!(error(&format!("x = {}1\n", "not ".repeat(20000)), "3.12").code, ErrorCode::TooDeeplyNested);
+    });
+}
+
+#[test]
+fn long_chains() {
+    with_main_stack(|| {
+        let chain = |n: usize, op: &str| format!("x = a{}\n", format!(" {} a", op).repeat(n));
+
+        assert_eq!(lower(&chain(4000, "+")).len(), 1);
+        assert_eq!(lower(&format!("x = a{}\n", ".b".repeat(4000))).len(), 1);
+        assert_eq!(lower(&format!("x = f{}\n", "()".repeat(4000))).len(), 1);
+        assert_eq!(error(&chain(20000, "+"), "3.12").code, ErrorCode::TooDeeplyNested);
+    });
+}
+
+#[test]
+fn long_elif_chains() {
+    with_main_stack(|| {
+        let chain = |n: usize| format!("if a:\n    pass\n{}else:\n    pass\n", "elif a:\n    pass\n".repeat(n));
+
+        assert_eq!(lower(&chain(501)).len(), 1);
+        assert_eq!(lower(&chain(1500)).len(), 1);
+        assert_eq!(error(&chain(10000), "3.12").code, ErrorCode::TooDeeplyNested);
+    });
+}
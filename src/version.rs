@@ -0,0 +1,446 @@
+use std::fmt;
+
+use super::ast::{Arguments, Comprehension, Expr, ExprKind, Operator, Stmt, StmtKind, TypeParam};
+use super::diagnostics::{Diagnostic, ErrorCode};
+use super::CompilationError;
+
+// versoes aceitas pelo --target, da mais antiga para a mais nova
+pub static TARGETS: [&str; 5] = ["2.7", "3.6", "3.8", "3.10", "3.12"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version(pub u8, pub u8);
+
+impl Version {
+    pub fn parse(version: &str) -> Option<Version> {
+        let (major, minor) = version.split_once('.')?;
+        Some(Version(major.parse().ok()?, minor.parse().ok()?))
+    }
+
+    pub fn is_python2(self) -> bool {
+        self.0 == 2
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&format!("{}.{}", self.0, self.1))
+    }
+}
+
+// uma construcao da linguagem que so existe a partir de uma versao
+#[derive(Debug)]
+pub struct Feature {
+    pub name: &'static str,
+    pub since: Version,
+    pub row: usize,
+    pub col: usize
+}
+
+fn feature(features: &mut Vec<Feature>, name: &'static str, since: Version, row: usize, col: usize) {
+    features.push(Feature {
+        name,
+        since,
+        row,
+        col
+    });
+}
+
+pub fn features(module: &[Stmt]) -> Vec<Feature> {
+    let mut features = Vec::new();
+    statements(module, &mut features);
+    features
+}
+
+// primeira construcao, na ordem do arquivo, que a versao alvo nao suporta
+pub fn check(module: &[Stmt], target: Version) -> Result<(), CompilationError> {
+    let unsupported = features(module)
+        .into_iter()
+        .filter(|f| f.since > target)
+        .min_by_key(|f| (f.row, f.col));
+
+    match unsupported {
+        Some(f) => {
+            let message = format!("{} require Python {} or greater (target is {})", f.name, f.since, target);
+            Err(CompilationError::SintaxError(Box::new(Diagnostic::new(ErrorCode::UnsupportedFeature, &message, f.row, f.col, 1))))
+        },
+        None => Ok(())
+    }
+}
+
+// cada construcao, na ordem do arquivo, que aumentou a versao minima
+pub fn minimum(module: &[Stmt], baseline: Version) -> (Version, Vec<Feature>) {
+    let mut features = features(module);
+    features.sort_by_key(|f| (f.row, f.col));
+
+    let mut oldest = baseline;
+    let mut bumps = Vec::new();
+
+    for f in features {
+        if f.since > oldest {
+            oldest = f.since;
+            bumps.push(f);
+        }
+    }
+
+    (oldest, bumps)
+}
+
+fn statements(body: &[Stmt], features: &mut Vec<Feature>) {
+    for stmt in body {
+        statement(stmt, features);
+    }
+}
+
+fn statement(stmt: &Stmt, features: &mut Vec<Feature>) {
+    match &stmt.kind {
+        StmtKind::FunctionDef { args, body, decorator_list, returns, is_async, .. } => {
+            if *is_async {
+                feature(features, "async functions", Version(3, 5), stmt.row, stmt.col);
+            }
+
+            decorators(decorator_list, features);
+            arguments(args, features);
+
+            if let Some(returns) = returns {
+                feature(features, "function annotations", Version(3, 0), returns.row, returns.col);
+                expression(returns, features);
+            }
+
+            statements(body, features);
+        },
+        StmtKind::ClassDef { bases, keywords, body, decorator_list, .. } => {
+            decorators(decorator_list, features);
+
+            if let Some(keyword) = keywords.first() {
+                feature(features, "class keyword arguments", Version(3, 0), keyword.value.row, keyword.value.col);
+            }
+
+            call_arguments(bases, keywords.iter().map(|k| (k.arg.is_none(), &k.value)), features);
+            statements(body, features);
+        },
+        StmtKind::Return(value) => optional_expression(value, features),
+        StmtKind::Assign { targets, value } => {
+            for t in targets {
+                target(t, features);
+            }
+
+            expression(value, features);
+        },
+        StmtKind::AugAssign { target: t, op, value } => {
+            if *op == Operator::MatMult {
+                feature(features, "matrix multiplication operators", Version(3, 5), stmt.row, stmt.col);
+            }
+
+            target(t, features);
+            expression(value, features);
+        },
+        StmtKind::AnnAssign { target: t, annotation, value, .. } => {
+            feature(features, "variable annotations", Version(3, 6), stmt.row, stmt.col);
+
+            if let Some(value) = value.as_ref().filter(|v| is_bare_tuple(v)) {
+                feature(features, "unparenthesized tuples in annotated assignments", Version(3, 8), value.row, value.col);
+            }
+
+            target(t, features);
+            expression(annotation, features);
+            optional_expression(value, features);
+        },
+        StmtKind::For { target: t, iter, body, orelse, is_async } => {
+            if *is_async {
+                feature(features, "async for loops", Version(3, 5), stmt.row, stmt.col);
+            }
+
+            if let ExprKind::Tuple(elts) = &iter.kind {
+                let starred = elts.iter().find(|e| matches!(e.kind, ExprKind::Starred(_)));
+
+                if let Some(starred) = starred.filter(|_| is_bare_tuple(iter)) {
+                    feature(features, "unparenthesized starred iterables in for loops", Version(3, 9), starred.row, starred.col);
+                }
+            }
+
+            target(t, features);
+            expression(iter, features);
+            statements(body, features);
+            statements(orelse, features);
+        },
+        StmtKind::While { test, body, orelse } | StmtKind::If { test, body, orelse } => {
+            expression(test, features);
+            statements(body, features);
+            statements(orelse, features);
+        },
+        StmtKind::With { items, body, is_async } => {
+            if *is_async {
+                feature(features, "async with statements", Version(3, 5), stmt.row, stmt.col);
+            }
+
+            for item in items {
+                expression(&item.context_expr, features);
+
+                if let Some(vars) = &item.optional_vars {
+                    target(vars, features);
+                }
+            }
+
+            statements(body, features);
+        },
+        StmtKind::Match { subject, cases } => {
+            feature(features, "match statements", Version(3, 10), stmt.row, stmt.col);
+            expression(subject, features);
+
+            for case in cases {
+                optional_expression(&case.guard, features);
+                statements(&case.body, features);
+            }
+        },
+        StmtKind::TypeAlias { type_params, value, .. } => {
+            feature(features, "type aliases", Version(3, 12), stmt.row, stmt.col);
+
+            for param in type_params {
+                if let TypeParam::TypeVar { bound: Some(bound), .. } = param {
+                    expression(bound, features);
+                }
+            }
+
+            expression(value, features);
+        },
+        StmtKind::Nonlocal(_) => feature(features, "nonlocal declarations", Version(3, 0), stmt.row, stmt.col),
+        StmtKind::Delete(values) => expressions(values, features),
+        StmtKind::Assert { test, msg } => {
+            expression(test, features);
+            optional_expression(msg, features);
+        },
+        StmtKind::Print { dest, values, .. } => {
+            optional_expression(dest, features);
+            expressions(values, features);
+        },
+        StmtKind::Exec { body, globals, locals } => {
+            expression(body, features);
+            optional_expression(globals, features);
+            optional_expression(locals, features);
+        },
+        StmtKind::Expr(value) => expression(value, features),
+        StmtKind::Global(_) | StmtKind::Pass | StmtKind::Break | StmtKind::Continue => {}
+    }
+}
+
+// antes do 3.9 o decorador so podia ser um nome pontuado, chamado ou nao
+fn decorators(decorator_list: &[Expr], features: &mut Vec<Feature>) {
+    for decorator in decorator_list {
+        let callee = match &decorator.kind {
+            ExprKind::Call { func, .. } => func,
+            _ => decorator
+        };
+
+        if !is_dotted_name(callee) {
+            feature(features, "arbitrary decorator expressions", Version(3, 9), decorator.row, decorator.col);
+        }
+
+        expression(decorator, features);
+    }
+}
+
+// uma tupla sem parenteses comeca no primeiro elemento, com parenteses comeca no '('
+fn is_bare_tuple(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Tuple(elts) => elts.first().is_some_and(|e| (e.row, e.col) == (expr.row, expr.col)),
+        _ => false
+    }
+}
+
+fn is_dotted_name(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Name(_) => true,
+        ExprKind::Attribute { value, .. } => is_dotted_name(value),
+        _ => false
+    }
+}
+
+fn arguments(args: &Arguments, features: &mut Vec<Feature>) {
+    if let Some(arg) = args.posonlyargs.first() {
+        feature(features, "positional-only parameters", Version(3, 8), arg.row, arg.col);
+    }
+
+    if let Some(arg) = args.kwonlyargs.first() {
+        feature(features, "keyword-only parameters", Version(3, 0), arg.row, arg.col);
+    }
+
+    let all = args.posonlyargs.iter()
+        .chain(args.args.iter())
+        .chain(args.vararg.iter())
+        .chain(args.kwonlyargs.iter())
+        .chain(args.kwarg.iter());
+
+    for arg in all {
+        if let Some(annotation) = &arg.annotation {
+            feature(features, "function annotations", Version(3, 0), annotation.row, annotation.col);
+            expression(annotation, features);
+        }
+
+        if let Some(default) = &arg.default {
+            expression(default, features);
+        }
+    }
+}
+
+// f(*a) e f(**k) sempre existiram, mais de um de cada so no 3.5
+fn call_arguments<'e>(
+    args: &'e [Expr],
+    keywords: impl Iterator<Item = (bool, &'e Expr)>,
+    features: &mut Vec<Feature>
+) {
+    let mut starred = 0;
+
+    for arg in args {
+        match &arg.kind {
+            ExprKind::Starred(value) => {
+                starred += 1;
+
+                if starred > 1 {
+                    feature(features, "unpacking generalizations", Version(3, 5), arg.row, arg.col);
+                }
+
+                expression(value, features);
+            },
+            _ => expression(arg, features)
+        }
+    }
+
+    let mut double_starred = 0;
+
+    for (unpacking, value) in keywords {
+        if unpacking {
+            double_starred += 1;
+
+            if double_starred > 1 {
+                feature(features, "unpacking generalizations", Version(3, 5), value.row, value.col);
+            }
+        }
+
+        expression(value, features);
+    }
+}
+
+fn target(expr: &Expr, features: &mut Vec<Feature>) {
+    match &expr.kind {
+        ExprKind::Starred(value) => {
+            feature(features, "starred assignment targets", Version(3, 0), expr.row, expr.col);
+            target(value, features);
+        },
+        ExprKind::List(elts) | ExprKind::Tuple(elts) => {
+            for elt in elts {
+                target(elt, features);
+            }
+        },
+        _ => expression(expr, features)
+    }
+}
+
+fn comprehensions(generators: &[Comprehension], features: &mut Vec<Feature>) {
+    for generator in generators {
+        if generator.is_async {
+            feature(features, "async comprehensions", Version(3, 6), generator.target.row, generator.target.col);
+        }
+
+        target(&generator.target, features);
+        expression(&generator.iter, features);
+        expressions(&generator.ifs, features);
+    }
+}
+
+fn optional_expression(expr: &Option<Expr>, features: &mut Vec<Feature>) {
+    if let Some(expr) = expr {
+        expression(expr, features);
+    }
+}
+
+fn expressions(exprs: &[Expr], features: &mut Vec<Feature>) {
+    for expr in exprs {
+        expression(expr, features);
+    }
+}
+
+fn expression(expr: &Expr, features: &mut Vec<Feature>) {
+    match &expr.kind {
+        ExprKind::BoolOp { values, .. } => expressions(values, features),
+        ExprKind::NamedExpr { value, .. } => {
+            feature(features, "assignment expressions", Version(3, 8), expr.row, expr.col);
+            expression(value, features);
+        },
+        ExprKind::BinOp { left, op, right } => {
+            if *op == Operator::MatMult {
+                feature(features, "matrix multiplication operators", Version(3, 5), expr.row, expr.col);
+            }
+
+            expression(left, features);
+            expression(right, features);
+        },
+        ExprKind::UnaryOp { operand, .. } => expression(operand, features),
+        ExprKind::Lambda { args, body } => {
+            arguments(args, features);
+            expression(body, features);
+        },
+        ExprKind::IfExp { test, body, orelse } => {
+            expression(test, features);
+            expression(body, features);
+            expression(orelse, features);
+        },
+        ExprKind::Await(value) => {
+            feature(features, "await expressions", Version(3, 5), expr.row, expr.col);
+            expression(value, features);
+        },
+        ExprKind::Yield(value) => {
+            if let Some(value) = value {
+                expression(value, features);
+            }
+        },
+        ExprKind::YieldFrom(value) => {
+            feature(features, "yield from expressions", Version(3, 3), expr.row, expr.col);
+            expression(value, features);
+        },
+        ExprKind::Compare { left, comparators, .. } => {
+            expression(left, features);
+            expressions(comparators, features);
+        },
+        ExprKind::Call { func, args, keywords } => {
+            expression(func, features);
+            call_arguments(args, keywords.iter().map(|k| (k.arg.is_none(), &k.value)), features);
+        },
+        ExprKind::JoinedStr(_) => feature(features, "f-strings", Version(3, 6), expr.row, expr.col),
+        ExprKind::Attribute { value, .. } => expression(value, features),
+        ExprKind::Subscript { value, slice } => {
+            expression(value, features);
+            expression(slice, features);
+        },
+        ExprKind::Dict { keys, values } => {
+            for (key, value) in keys.iter().zip(values) {
+                match key {
+                    Some(key) => expression(key, features),
+                    None => feature(features, "unpacking generalizations", Version(3, 5), value.row, value.col)
+                }
+
+                expression(value, features);
+            }
+        },
+        ExprKind::ListComp { elt, generators } | ExprKind::SetComp { elt, generators } | ExprKind::GeneratorExp { elt, generators } => {
+            expression(elt, features);
+            comprehensions(generators, features);
+        },
+        ExprKind::DictComp { key, value, generators } => {
+            expression(key, features);
+            expression(value, features);
+            comprehensions(generators, features);
+        },
+        // fora de um alvo de atribuicao, *x so aparece dentro de um display
+        ExprKind::Starred(value) => {
+            feature(features, "unpacking generalizations", Version(3, 5), expr.row, expr.col);
+            expression(value, features);
+        },
+        ExprKind::Set(elts) | ExprKind::List(elts) | ExprKind::Tuple(elts) => expressions(elts, features),
+        ExprKind::Slice { lower, upper, step } => {
+            for bound in [lower, upper, step].iter().filter_map(|b| b.as_ref()) {
+                expression(bound, features);
+            }
+        },
+        ExprKind::Constant(_) | ExprKind::Name(_) => {}
+    }
+}